@@ -0,0 +1,145 @@
+use macroquad::prelude::{Color, draw_circle_lines, draw_line, screen_height, screen_width};
+use nalgebra::Vector2;
+use crate::params::Contact;
+
+#[derive(Debug, Clone)]
+pub enum Shape {
+    /// Thin wall between two points, snow is stopped when it crosses it from either side.
+    Segment { a: Vector2<f64>, b: Vector2<f64> },
+    Circle { center: Vector2<f64>, radius: f64 },
+    /// Closed solid polygon, convex or concave, vertices in either winding order.
+    Polygon(Vec<Vector2<f64>>),
+}
+
+fn segment_distance(p: Vector2<f64>, a: Vector2<f64>, b: Vector2<f64>) -> f64 {
+    let ab = b - a;
+    let t = ((p - a).dot(&ab) / ab.norm_squared()).clamp(0.0, 1.0);
+    (p - (a + ab * t)).norm()
+}
+
+/// Signed distance to a closed polygon, negative inside.
+pub fn polygon_distance(p: Vector2<f64>, vertices: &[Vector2<f64>]) -> f64 {
+    let mut distance = f64::INFINITY;
+    let mut inside = false;
+    for i in 0..vertices.len() {
+        let a = vertices[i];
+        let b = vertices[(i + 1) % vertices.len()];
+        distance = distance.min(segment_distance(p, a, b));
+        // Even-odd rule, so concave polygons work too
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    if inside { -distance } else { distance }
+}
+
+impl Shape {
+    /// Distance to the shape, negative inside circles and polygons. Segments have no inside.
+    pub fn signed_distance(&self, p: Vector2<f64>) -> f64 {
+        match self {
+            Shape::Segment { a, b } => segment_distance(p, *a, *b),
+            Shape::Circle { center, radius } => (p - center).norm() - radius,
+            Shape::Polygon(vertices) => polygon_distance(p, vertices),
+        }
+    }
+
+    fn translate(&mut self, offset: Vector2<f64>) {
+        match self {
+            Shape::Segment { a, b } => {
+                *a += offset;
+                *b += offset;
+            }
+            Shape::Circle { center, .. } => *center += offset,
+            Shape::Polygon(vertices) => {
+                for vertex in vertices.iter_mut() {
+                    *vertex += offset;
+                }
+            }
+        }
+    }
+}
+
+/// A collider in simulation coordinates, i.e. the unit square the particles live in.
+pub struct Collider {
+    pub shape: Shape,
+    pub contact: Contact,
+    pub vel: Vector2<f64>,
+    pub color: Color,
+}
+
+impl Collider {
+    pub fn new(shape: Shape, contact: Contact, vel: Vector2<f64>, color: Color) -> Self {
+        Collider {
+            shape,
+            contact,
+            vel,
+            color,
+        }
+    }
+
+    fn normal(&self, position: Vector2<f64>) -> Vector2<f64> {
+        let eps = 1e-6;
+        let dx = Vector2::new(eps, 0.0);
+        let dy = Vector2::new(0.0, eps);
+        let gradient = Vector2::new(
+            self.shape.signed_distance(position + dx) - self.shape.signed_distance(position - dx),
+            self.shape.signed_distance(position + dy) - self.shape.signed_distance(position - dy),
+        );
+        if gradient.norm() > 0.0 { gradient.normalize() } else { Vector2::new(0.0, -1.0) }
+    }
+
+    /// Applies the boundary condition to a point that touches the collider or would pass into it during `dt`.
+    pub fn collide(&self, position: Vector2<f64>, velocity: Vector2<f64>, dt: f64, tolerance: f64) -> Vector2<f64> {
+        let vel_rel = velocity - self.vel;
+        let next_pos = position + vel_rel * dt;
+
+        let normal = match &self.shape {
+            Shape::Segment { a, b } => {
+                // Like the 3D `Plane`: collide when the point is on the segment or crosses its line within its extent
+                let ab = b - a;
+                let line_normal = Vector2::new(-ab.y, ab.x).normalize();
+                let offset = (position - a).dot(&line_normal);
+                let offset_next = (next_pos - a).dot(&line_normal);
+                let t = (next_pos - a).dot(&ab) / ab.norm_squared();
+                if !(0.0..=1.0).contains(&t) || (offset.abs() >= tolerance && offset * offset_next >= 0.0) {
+                    return velocity;
+                }
+                if offset > 0.0 { line_normal } else { -line_normal }
+            }
+            _ => {
+                if self.shape.signed_distance(position) > 0.0 && self.shape.signed_distance(next_pos) > 0.0 {
+                    return velocity;
+                }
+                self.normal(position)
+            }
+        };
+
+        self.vel + self.contact.respond(vel_rel, normal)
+    }
+
+    pub fn update_position(&mut self, dt: f64) {
+        self.shape.translate(self.vel * dt);
+    }
+
+    pub fn draw(&self) {
+        let to_screen = |p: Vector2<f64>| ((p.x as f32) * screen_width() / 2.0, (p.y as f32) * screen_height() / 2.0);
+        match &self.shape {
+            Shape::Segment { a, b } => {
+                let (ax, ay) = to_screen(*a);
+                let (bx, by) = to_screen(*b);
+                draw_line(ax, ay, bx, by, 2.0, self.color);
+            }
+            Shape::Circle { center, radius } => {
+                let (x, y) = to_screen(*center);
+                draw_circle_lines(x, y, (*radius as f32) * screen_width() / 2.0, 2.0, self.color);
+            }
+            Shape::Polygon(vertices) => {
+                for i in 0..vertices.len() {
+                    let (ax, ay) = to_screen(vertices[i]);
+                    let (bx, by) = to_screen(vertices[(i + 1) % vertices.len()]);
+                    draw_line(ax, ay, bx, by, 2.0, self.color);
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
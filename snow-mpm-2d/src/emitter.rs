@@ -0,0 +1,72 @@
+use nalgebra::Vector2;
+use rand::Rng;
+
+/// Axis-aligned rectangle in simulation coordinates.
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    pub min: Vector2<f64>,
+    pub max: Vector2<f64>,
+}
+
+impl Region {
+    pub fn new(min: Vector2<f64>, max: Vector2<f64>) -> Self {
+        Region {
+            min,
+            max,
+        }
+    }
+
+    pub fn contains(&self, position: Vector2<f64>) -> bool {
+        position.x >= self.min.x && position.x <= self.max.x && position.y >= self.min.y && position.y <= self.max.y
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> Vector2<f64> {
+        Vector2::new(rng.gen_range(self.min.x..=self.max.x), rng.gen_range(self.min.y..=self.max.y))
+    }
+}
+
+/// Spawns particles inside a region at a steady rate, e.g. snowfall along the top of the domain.
+pub struct Emitter {
+    pub region: Region,
+    /// Particles per second.
+    pub rate: f64,
+    pub vel: Vector2<f64>,
+    pub particle_mass: f64,
+    pub temperature: f64,
+    /// Simulation time window during which the emitter runs, open-ended if `end` is `None`.
+    pub start: f64,
+    pub end: Option<f64>,
+    pending: f64,
+}
+
+impl Emitter {
+    pub fn new(region: Region, rate: f64, vel: Vector2<f64>, particle_mass: f64, temperature: f64) -> Self {
+        Emitter {
+            region,
+            rate,
+            vel,
+            particle_mass,
+            temperature,
+            start: 0.0,
+            end: None,
+            pending: 0.0,
+        }
+    }
+
+    pub fn with_window(mut self, start: f64, end: Option<f64>) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Number of particles to spawn in the step starting at `time`. Fractions are carried over to later steps.
+    pub fn count(&mut self, time: f64, dt: f64) -> usize {
+        if time < self.start || self.end.is_some_and(|end| time >= end) {
+            return 0;
+        }
+        self.pending += self.rate * dt;
+        let count = self.pending.floor();
+        self.pending -= count;
+        count as usize
+    }
+}
\ No newline at end of file
@@ -3,8 +3,14 @@ use macroquad::prelude::{draw_circle, screen_height, screen_width};
 use macroquad::shapes::draw_line;
 use nalgebra::{DMatrix, Matrix2, Matrix4, Vector2};
 use rayon::prelude::*;
-use crate::params::Params;
-use crate::particle::Particle;
+use crate::collider::Collider;
+use crate::emitter::{Emitter, Region};
+use crate::params::{DomainBoundary, Params};
+use crate::mask::ImageMask;
+use crate::noise::Heterogeneity;
+use crate::particle::{Particle, Phase};
+use crate::sampling;
+use crate::volume::Volume;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 struct GridNode {
@@ -12,6 +18,10 @@ struct GridNode {
     vel: Vector2<f64>,
     vel_new: Vector2<f64>,
     active: bool,
+    heat_capacity: f64,
+    temp: f64,
+    temp_new: f64,
+    heat: f64,
 }
 
 impl GridNode {
@@ -21,6 +31,10 @@ impl GridNode {
             vel: Vector2::new(0.0, 0.0),
             vel_new: Vector2::new(0.0, 0.0),
             active: false,
+            heat_capacity: 0.0,
+            temp: 0.0,
+            temp_new: 0.0,
+            heat: 0.0,
         }
     }
 }
@@ -31,6 +45,10 @@ pub struct Grid {
     cc: usize,
     nodes: DMatrix<GridNode>,
     pub particles: Vec<Particle>,
+    pub colliders: Vec<Collider>,
+    emitters: Vec<Emitter>,
+    sinks: Vec<Region>,
+    time: f64,
     params: Params,
 }
 
@@ -45,6 +63,10 @@ impl Grid {
             cc,
             nodes,
             particles: vec![],
+            colliders: vec![],
+            emitters: vec![],
+            sinks: vec![],
+            time: 0.0,
             params,
         }
     }
@@ -53,12 +75,91 @@ impl Grid {
         self.particles.push(particle);
     }
 
+    /// Fills a volume, clipped to the domain, with snow particles one particle diameter apart. Every particle
+    /// gets an even share of the volume's mass.
+    pub fn add_volume(&mut self, volume: &Volume, vel: Vector2<f64>, heterogeneity: Option<&Heterogeneity>) {
+        let mut rng = rand::thread_rng();
+        let (min, max) = volume.bounds();
+        let min = min.sup(&Vector2::zeros());
+        let max = max.inf(&Vector2::repeat(1.0));
+        if sampling::is_empty(min, max) {
+            return;
+        }
+        let spacing = self.params.particle_diam;
+        let positions = sampling::poisson_disk(min, max, spacing, |p| volume.contains(p), &mut rng);
+        let area = sampling::estimate_area(min, max, spacing / 2.0, |p| volume.contains(p));
+        let mass = self.params.density * area / positions.len().max(1) as f64;
+        for pos in positions {
+            let mut particle = Particle::new(pos, vel, mass, self.params.snow_temperature);
+            if let Some(heterogeneity) = heterogeneity {
+                heterogeneity.apply(&mut particle);
+            }
+            self.particles.push(particle);
+        }
+    }
+
+    /// Rasterises an image mask into particles one particle diameter apart, layer by layer. Every particle gets
+    /// an even share of the mass of the pixels of its layer.
+    pub fn add_image_mask(&mut self, mask: &ImageMask, vel: Vector2<f64>, heterogeneity: Option<&Heterogeneity>) {
+        let mut rng = rand::thread_rng();
+        let min = mask.min.sup(&Vector2::zeros());
+        let max = mask.max.inf(&Vector2::repeat(1.0));
+        for (index, layer) in mask.layers.iter().enumerate() {
+            let positions = sampling::poisson_disk(min, max, self.params.particle_diam, |p| mask.layer_at(p) == Some(index), &mut rng);
+            let mass = layer.density * mask.area(index, min, max) / positions.len().max(1) as f64;
+            for pos in positions {
+                let mut particle = Particle::new(pos, vel, mass, layer.temperature);
+                particle.phase = layer.phase;
+                if layer.phase == Phase::Water {
+                    particle.latent_heat = self.params.latent_heat;
+                }
+                particle.stiffness = layer.stiffness;
+                particle.strength = layer.strength;
+                if let Some(heterogeneity) = heterogeneity {
+                    heterogeneity.apply(&mut particle);
+                }
+                self.particles.push(particle);
+            }
+        }
+    }
+
+    pub fn add_collider(&mut self, collider: Collider) {
+        self.colliders.push(collider);
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) {
+        self.emitters.push(emitter);
+    }
+
+    /// Particles that enter `sink` are deleted.
+    pub fn add_sink(&mut self, sink: Region) {
+        self.sinks.push(sink);
+    }
+
+    /// Spawns this step's particles from the emitters and deletes the ones inside sinks.
+    pub fn emit_particles(&mut self) {
+        let mut rng = rand::thread_rng();
+        for emitter in self.emitters.iter_mut() {
+            for _ in 0..emitter.count(self.time, self.params.dt) {
+                let pos = emitter.region.sample(&mut rng);
+                self.particles.push(Particle::new(pos, emitter.vel, emitter.particle_mass, emitter.temperature));
+            }
+        }
+        let sinks = &self.sinks;
+        self.particles.retain(|p| !sinks.iter().any(|sink| sink.contains(p.pos)));
+        self.time += self.params.dt;
+    }
+
     pub fn reset_parameters(&mut self) {
         unsafe {
             self.nodes.data.as_vec_mut().par_iter_mut().for_each(|n| {
                 n.mass = 0.0;
                 n.vel = Vector2::new(0.0, 0.0);
                 n.vel_new = Vector2::new(0.0, 0.0);
+                n.heat_capacity = 0.0;
+                n.temp = 0.0;
+                n.temp_new = 0.0;
+                n.heat = 0.0;
             });
         }
 
@@ -79,11 +180,12 @@ impl Grid {
             for i in 0..4 {
                 for j in 0..4 {
                     let distance = Vector2::new(
-                        p.pos.x / self.cs - (grid_index.x + i - 1) as f64,
-                        p.pos.y / self.cs - (grid_index.y + j - 1) as f64,
+                        p.pos.x / self.cs - (grid_index.x as f64 + i as f64 - 1.0),
+                        p.pos.y / self.cs - (grid_index.y as f64 + j as f64 - 1.0),
                     );
-                    let index_i = grid_index.x + i;
-                    let index_j = grid_index.y + j;
+                    let Some((index_i, index_j)) = node_index(grid_index, i, j, self.cc, self.params.periodic()) else {
+                        continue;
+                    };
                     let wy = Self::n(distance.y);
                     let dy = Self::n_x(distance.y);
                     let wx = Self::n(distance.x);
@@ -106,8 +208,9 @@ impl Grid {
             );
             for i in 0..4 {
                 for j in 0..4 {
-                    let index_i = grid_index.x + i;
-                    let index_j = grid_index.y + j;
+                    let Some((index_i, index_j)) = node_index(grid_index, i, j, self.cc, self.params.periodic()) else {
+                        continue;
+                    };
                     let w = p.w[(i, j)];
                     if w > self.params.bspline_epsilon {
                         self.nodes[(index_i, index_j)].vel += p.vel * (w * p.mass);
@@ -132,8 +235,9 @@ impl Grid {
             let mut density = 0.0;
             for i in 0..4 {
                 for j in 0..4 {
-                    let index_i = grid_index.x + i;
-                    let index_j = grid_index.y + j;
+                    let Some((index_i, index_j)) = node_index(grid_index, i, j, self.cc, self.params.periodic()) else {
+                        continue;
+                    };
                     let w = p.w[(i, j)];
                     if w > self.params.bspline_epsilon {
                         density += w * self.nodes[(index_i, index_j)].mass;
@@ -155,8 +259,9 @@ impl Grid {
             let v_t = svd_result.v_t.unwrap();
             let re = w * v_t;
             // println!("JP, JE, W, V, RE, def_elastic: {}, {}, {:?}, {:?}, {:?} {:?}", jp, je, w, v, re, p.def_elastic);
-            let mu = self.params.mu_0 * (self.params.hardening_coefficient * (1.0 - jp)).exp();
-            let lambda = self.params.lambda_0 * (self.params.hardening_coefficient * (1.0 - jp)).exp();
+            let hardening = self.params.hardening_law.factor(self.params.hardening_coefficient, jp);
+            let mu = if p.phase == Phase::Water { 0.0 } else { self.params.mu_0 * p.stiffness * hardening };
+            let lambda = self.params.lambda_0 * p.stiffness * hardening;
             let sigma = 2.0 * mu / jp * (p.e_d - re) * p.e_d.transpose() + lambda / jp * (je - 1.0) * je * Matrix2::identity();
             let jn = (p.e_d * p.e_p).determinant();
             let v_n = jn * p.vol;
@@ -167,8 +272,9 @@ impl Grid {
             );
             for i in 0..4 {
                 for j in 0..4 {
-                    let index_i = grid_index.x + i;
-                    let index_j = grid_index.y + j;
+                    let Some((index_i, index_j)) = node_index(grid_index, i, j, self.cc, self.params.periodic()) else {
+                        continue;
+                    };
                     let w = p.w[(i, j)];
                     if w > self.params.bspline_epsilon {
                         self.nodes[(index_i, index_j)].vel_new -= energy * Vector2::new(p.w_d_x[(i, j)], p.w_d_y[(i, j)]);
@@ -179,6 +285,158 @@ impl Grid {
         }
     }
 
+    fn heat_capacity(&self, phase: Phase) -> f64 {
+        match phase {
+            Phase::Snow => self.params.heat_capacity_snow,
+            Phase::Water => self.params.heat_capacity_water,
+        }
+    }
+
+    fn conductivity(&self, phase: Phase) -> f64 {
+        match phase {
+            Phase::Snow => self.params.conductivity_snow,
+            Phase::Water => self.params.conductivity_water,
+        }
+    }
+
+    /// Whether node (i, j) lies in the boundary layer of a face that is a wall, open and periodic faces are not heated.
+    fn is_wall_node(&self, i: usize, j: usize) -> bool {
+        let i = i as f64;
+        let j = j as f64;
+        let far = self.cc as f64 - self.params.bspline_radius - 1.0;
+        let wall = |face: usize| self.params.domain[face] == DomainBoundary::Wall;
+        (i < self.params.bspline_radius && wall(0)) || (i > far && wall(1))
+            || (j < self.params.bspline_radius && wall(2)) || (j > far && wall(3))
+    }
+
+    pub fn p2g_heat(&mut self) {
+        for p in self.particles.iter() {
+            let heat_capacity = p.mass * self.heat_capacity(p.phase);
+            let grid_index = Vector2::new(
+                (p.pos.x / self.cs).floor() as usize,
+                (p.pos.y / self.cs).floor() as usize,
+            );
+            for i in 0..4 {
+                for j in 0..4 {
+                    let Some((index_i, index_j)) = node_index(grid_index, i, j, self.cc, self.params.periodic()) else {
+                        continue;
+                    };
+                    let w = p.w[(i, j)];
+                    if w > self.params.bspline_epsilon {
+                        self.nodes[(index_i, index_j)].heat_capacity += w * heat_capacity;
+                        self.nodes[(index_i, index_j)].temp += w * heat_capacity * p.temperature;
+                    }
+                }
+            }
+        }
+        unsafe {
+            self.nodes.data.as_vec_mut().par_iter_mut()
+                .filter(|n| n.active && n.heat_capacity > 0.0)
+                .for_each(|n| n.temp /= n.heat_capacity);
+        }
+    }
+
+    pub fn compute_heat_diffusion(&mut self) {
+        if let Some(wall_temperature) = self.params.wall_temperature {
+            for j in 0..self.cc {
+                for i in 0..self.cc {
+                    if self.is_wall_node(i, j) {
+                        self.nodes[(i, j)].temp = wall_temperature;
+                    }
+                }
+            }
+        }
+
+        for p in self.particles.iter() {
+            let grid_index = Vector2::new(
+                (p.pos.x / self.cs).floor() as usize,
+                (p.pos.y / self.cs).floor() as usize,
+            );
+            let mut grad_temp = Vector2::new(0.0, 0.0);
+            for i in 0..4 {
+                for j in 0..4 {
+                    let Some((index_i, index_j)) = node_index(grid_index, i, j, self.cc, self.params.periodic()) else {
+                        continue;
+                    };
+                    if p.w[(i, j)] > self.params.bspline_epsilon {
+                        grad_temp += self.nodes[(index_i, index_j)].temp * Vector2::new(p.w_d_x[(i, j)], p.w_d_y[(i, j)]);
+                    }
+                }
+            }
+            let flux = p.vol * self.conductivity(p.phase) * grad_temp;
+            for i in 0..4 {
+                for j in 0..4 {
+                    let Some((index_i, index_j)) = node_index(grid_index, i, j, self.cc, self.params.periodic()) else {
+                        continue;
+                    };
+                    if p.w[(i, j)] > self.params.bspline_epsilon {
+                        self.nodes[(index_i, index_j)].heat -= flux.dot(&Vector2::new(p.w_d_x[(i, j)], p.w_d_y[(i, j)]));
+                    }
+                }
+            }
+        }
+
+        unsafe {
+            self.nodes.data.as_vec_mut().par_iter_mut()
+                .filter(|n| n.active && n.heat_capacity > 0.0)
+                .for_each(|n| n.temp_new = n.temp + self.params.dt * n.heat / n.heat_capacity);
+        }
+
+        if let Some(wall_temperature) = self.params.wall_temperature {
+            for j in 0..self.cc {
+                for i in 0..self.cc {
+                    if self.is_wall_node(i, j) {
+                        self.nodes[(i, j)].temp_new = wall_temperature;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn update_temperatures(&mut self) {
+        self.particles.par_iter_mut().for_each(|p| {
+            let mut pic = 0.0;
+            let mut flip = p.temperature;
+            let grid_index = Vector2::new(
+                (p.pos.x / self.cs).floor() as usize,
+                (p.pos.y / self.cs).floor() as usize,
+            );
+            for i in 0..4 {
+                for j in 0..4 {
+                    let Some((index_i, index_j)) = node_index(grid_index, i, j, self.cc, self.params.periodic()) else {
+                        continue;
+                    };
+                    let w = p.w[(i, j)];
+                    if w > self.params.bspline_epsilon {
+                        pic += self.nodes[(index_i, index_j)].temp_new * w;
+                        flip += (self.nodes[(index_i, index_j)].temp_new - self.nodes[(index_i, index_j)].temp) * w;
+                    }
+                }
+            }
+            p.temperature = flip * self.params.flip_pic_ratio + pic * (1.0 - self.params.flip_pic_ratio);
+
+            // Store energy above the melting point as latent heat until the particle melts, and release it when refreezing
+            let params = &self.params;
+            if p.phase == Phase::Snow && p.temperature > params.melting_point {
+                p.latent_heat += (p.temperature - params.melting_point) * params.heat_capacity_snow;
+                p.temperature = params.melting_point;
+                if p.latent_heat >= params.latent_heat {
+                    p.temperature += (p.latent_heat - params.latent_heat) / params.heat_capacity_water;
+                    p.latent_heat = params.latent_heat;
+                    p.phase = Phase::Water;
+                }
+            } else if p.phase == Phase::Water && p.temperature < params.melting_point {
+                p.latent_heat -= (params.melting_point - p.temperature) * params.heat_capacity_water;
+                p.temperature = params.melting_point;
+                if p.latent_heat <= 0.0 {
+                    p.temperature += p.latent_heat / params.heat_capacity_snow;
+                    p.latent_heat = 0.0;
+                    p.phase = Phase::Snow;
+                }
+            }
+        });
+    }
+
     pub fn update_grid_velocities(&mut self) {
         unsafe {
             self.nodes.data.as_vec_mut().par_iter_mut()
@@ -195,13 +453,24 @@ impl Grid {
                     let j = index / self.cc;
                     let i = index % self.cc;
                     let new_pos = n.vel_new * (self.params.dt / self.cs) + Vector2::new(i as f64, j as f64);
-                    if new_pos.x < self.params.bspline_radius || new_pos.x > self.cc as f64 - self.params.bspline_radius - 1.0 {
-                        n.vel_new.x = 0.0;
-                        n.vel_new.y *= 0.9;
+                    let lower = self.params.bspline_radius;
+                    let upper = self.cc as f64 - self.params.bspline_radius - 1.0;
+                    let wall = |face: usize| self.params.domain[face] == DomainBoundary::Wall;
+                    if new_pos.x < lower && wall(0) {
+                        n.vel_new = self.params.wall_contact.respond(n.vel_new, Vector2::new(1.0, 0.0));
+                    } else if new_pos.x > upper && wall(1) {
+                        n.vel_new = self.params.wall_contact.respond(n.vel_new, Vector2::new(-1.0, 0.0));
                     }
-                    if new_pos.y < self.params.bspline_radius || new_pos.y > self.cc as f64 - self.params.bspline_radius - 1.0 {
-                        n.vel_new.y = 0.0;
-                        n.vel_new.x *= 0.9;
+                    if new_pos.y < lower && wall(2) {
+                        n.vel_new = self.params.wall_contact.respond(n.vel_new, Vector2::new(0.0, 1.0));
+                    } else if new_pos.y > upper && wall(3) {
+                        n.vel_new = self.params.wall_contact.respond(n.vel_new, Vector2::new(0.0, -1.0));
+                    }
+
+                    // Node (i, j) carries the weights of the point one cell up and left of its index
+                    let position = Vector2::new(i as f64 - 1.0, j as f64 - 1.0) * self.cs;
+                    for collider in &self.colliders {
+                        n.vel_new = collider.collide(position, n.vel_new, self.params.dt, self.cs);
                     }
                 });
         }
@@ -218,8 +487,9 @@ impl Grid {
             );
             for i in 0..4 {
                 for j in 0..4 {
-                    let index_i = grid_index.x + i;
-                    let index_j = grid_index.y + j;
+                    let Some((index_i, index_j)) = node_index(grid_index, i, j, self.cc, self.params.periodic()) else {
+                        continue;
+                    };
                     let w = p.w[(i, j)];
                     if w > self.params.bspline_epsilon {
                         pic += self.nodes[(index_i, index_j)].vel_new * w;
@@ -228,26 +498,54 @@ impl Grid {
                     }
                 }
             }
-            p.vel = flip * 0.95 + pic * (1.0 - 0.95);
+            p.vel = flip * self.params.flip_pic_ratio + pic * (1.0 - self.params.flip_pic_ratio);
         });
     }
 
+    pub fn collision_particles(&mut self) {
+        self.particles.par_iter_mut().for_each(|p| {
+            for collider in &self.colliders {
+                p.vel = collider.collide(p.pos, p.vel, self.params.dt, self.params.particle_diam);
+            }
+        });
+    }
+
+    pub fn update_colliders(&mut self) {
+        for collider in self.colliders.iter_mut() {
+            collider.update_position(self.params.dt);
+        }
+    }
+
     pub fn update_deformation_gradient(&mut self) {
         self.particles.par_iter_mut().for_each(|p| {
             p.vel_d = Matrix2::identity() + self.params.dt * p.vel_d;
             p.e_d = p.vel_d * p.e_d;
             // println!("def_elastic: {:?}", p.def_elastic);
             let f_all = p.e_d * p.e_p;
+            if p.phase == Phase::Water {
+                // Fluids only keep the volumetric part of the elastic deformation
+                p.e_d = Matrix2::identity() * p.e_d.determinant().sqrt();
+                p.e_p = p.e_d.try_inverse().unwrap() * f_all;
+                return;
+            }
             let svd_result = p.e_d.svd(true, true);
             let w = svd_result.u.unwrap();
             let v_t = svd_result.v_t.unwrap();
             let mut e = Matrix2::from_diagonal(&svd_result.singular_values);
             // println!("w, v, e: {:?}, {:?}, {:?}", w, v, e);
+            let critical_compression = 1.0 - (1.0 - self.params.critical_compression) * p.strength;
+            let critical_stretch = 1.0 + (self.params.critical_stretch - 1.0) * p.strength;
             for i in 0..2 {
-                if e[(i, i)] < self.params.critical_compression {
-                    e[(i, i)] = self.params.critical_compression;
-                } else if e[(i, i)] > self.params.critical_stretch {
-                    e[(i, i)] = self.params.critical_stretch;
+                let trial = e[(i, i)];
+                if e[(i, i)] < critical_compression {
+                    e[(i, i)] = critical_compression;
+                } else if e[(i, i)] > critical_stretch {
+                    e[(i, i)] = critical_stretch;
+                }
+                // Duvaut-Lions viscoplasticity: only relax part of the way towards the yield surface
+                if let Some(tau) = self.params.relaxation_time {
+                    let r = self.params.dt / tau;
+                    e[(i, i)] = (trial + r * e[(i, i)]) / (1.0 + r);
                 }
             }
             p.e_p = v_t.transpose() * e.try_inverse().unwrap() * w.transpose() * f_all;
@@ -262,6 +560,26 @@ impl Grid {
         });
     }
 
+    /// Keeps particles where their 4x4 stencil fits on the grid: clamps them at walls, wraps them across periodic
+    /// faces and deletes the ones that left through an open face.
+    pub fn enforce_domain(&mut self) {
+        let upper = (self.cc - 3) as f64 * self.cs - 1e-9;
+        let domain = self.params.domain;
+        self.particles.retain_mut(|p| {
+            for axis in 0..2 {
+                if p.pos[axis] < 0.0 || p.pos[axis] > upper {
+                    let face = if p.pos[axis] < 0.0 { 2 * axis } else { 2 * axis + 1 };
+                    match domain[face] {
+                        DomainBoundary::Wall => p.pos[axis] = p.pos[axis].clamp(0.0, upper),
+                        DomainBoundary::Open => return false,
+                        DomainBoundary::Periodic => p.pos[axis] = p.pos[axis].rem_euclid(1.0).min(1.0 - 1e-9),
+                    }
+                }
+            }
+            true
+        });
+    }
+
     fn n(x: f64) -> f64 {
         let x = x.abs();
         let x2 = x * x;
@@ -331,4 +649,28 @@ impl Grid {
             count += 1;
         }
     }
+}
+
+/// Storage index of stencil node `(i, j)` of the particle in cell `grid_index`, which is one cell up and left of the
+/// node's position. Periodic axes wrap around, on the others a node past the last one is `None`; its weight is zero
+/// anyway since `enforce_domain` keeps particles three cells from the far faces.
+fn node_index(grid_index: Vector2<usize>, i: usize, j: usize, cc: usize, periodic: [bool; 2]) -> Option<(usize, usize)> {
+    let wrap = |index: usize, periodic: bool| if periodic { Some(index % cc) } else if index < cc { Some(index) } else { None };
+    Some((wrap(grid_index.x + i, periodic[0])?, wrap(grid_index.y + j, periodic[1])?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_wall_faces_are_held_at_the_wall_temperature() {
+        let params = Params::new().with_domain([DomainBoundary::Periodic, DomainBoundary::Periodic, DomainBoundary::Open, DomainBoundary::Wall]);
+        let grid = Grid::new(16, params);
+        assert!(!grid.is_wall_node(0, 8));
+        assert!(!grid.is_wall_node(15, 8));
+        assert!(!grid.is_wall_node(8, 0));
+        assert!(grid.is_wall_node(8, 15));
+        assert!(!grid.is_wall_node(8, 8));
+    }
 }
\ No newline at end of file
@@ -1,7 +1,14 @@
 mod params;
 mod particle;
 mod grid;
-
+mod noise;
+mod collider;
+mod emitter;
+mod mask;
+mod sampling;
+mod volume;
+
+use std::f64::consts::PI;
 use std::time::Instant;
 use macroquad::input::{is_key_pressed, KeyCode};
 use macroquad::prelude::{clear_background, Color, draw_text, get_fps, next_frame, screen_height, screen_width};
@@ -10,51 +17,136 @@ use crate::grid::Grid;
 use crate::particle::Particle;
 use nalgebra::{Vector2};
 use rand::prelude::StdRng;
-use rand::{Rng, SeedableRng};
-use crate::params::Params;
-
-#[macroquad::main("Snow simulation")]
-async fn main() {
-    request_new_screen_size(1000.0, 1000.0);
+use rand::SeedableRng;
+use crate::params::{BoundaryCondition, Contact, DomainBoundary, HardeningLaw, HardeningTable, Params};
+use crate::collider::{Collider, Shape};
+use crate::emitter::{Emitter, Region};
+use crate::mask::{ImageMask, MaskLayer};
+use crate::noise::Heterogeneity;
+use crate::particle::Phase;
+use crate::volume::Volume;
+
+/// Scenes selectable with the number keys.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scene {
+    /// Two snowballs thrown at each other.
+    Snowballs,
+    /// A slab of snow on a slope and a hollowed igloo with a doorway, built from volumes.
+    Volumes,
+    /// Packed snow holding back water, painted in an image mask.
+    Mask,
+    /// Snowfall onto a ball and a wedge in a domain that wraps around sideways and is open at the top, drained by a sink.
+    Snowfall,
+}
 
+fn build_scene(scene: Scene) -> Grid {
     let mut rng = StdRng::seed_from_u64(20);
-    let params = Params::new();
-    let particle_mass = params.particle_mass.clone();
-    let particle_area = params.particle_area.clone();
-    let mut grid = Grid::new(64, params);
-
-    let size1: f64 = 0.3;
-    let r1: f64 = size1 / 2.0;
-    let a1: f64 = size1 * size1;
-    let pc1: usize = (a1 / particle_area) as usize;
-    for _ in 0..pc1 {
-        let pos = Vector2::new(0.1 + size1 * rng.gen::<f64>(), 0.3 + size1 * rng.gen::<f64>());
-        if (pos - Vector2::new(0.1 + r1, 0.3 + r1)).norm() < r1 {
-            let particle = Particle::new(pos, Vector2::new(5.0, 0.0), particle_mass);
-            grid.add_particle(particle);
+    let mut params = Params::new();
+    let particle_diam = params.particle_diam;
+    let density = params.density;
+    let snow_temperature = params.snow_temperature;
+    let heterogeneity = Heterogeneity::new(7, 20.0, 0.1, 0.3, 0.2);
+    let collider_color = Color::new(0.6, 0.4, 0.2, 1.0);
+    let contact = Contact::new(BoundaryCondition::Separating, 0.3, 0.2);
+
+    let mut grid = match scene {
+        Scene::Snowballs => {
+            let mut grid = Grid::new(64, params);
+
+            // Poisson-disk sampling spaces the particles one particle diameter apart, each gets an even share of the ball's mass
+            let size1: f64 = 0.3;
+            let r1: f64 = size1 / 2.0;
+            let c1 = Vector2::new(0.1 + r1, 0.3 + r1);
+            let positions1 = sampling::poisson_disk(Vector2::new(0.1, 0.3), Vector2::new(0.1 + size1, 0.3 + size1), particle_diam, |p| (p - c1).norm() < r1, &mut rng);
+            let mass1 = density * PI * r1 * r1 / positions1.len() as f64;
+            for pos in positions1 {
+                let particle = Particle::new(pos, Vector2::new(5.0, 0.0), mass1, snow_temperature);
+                grid.add_particle(particle);
+            }
+
+            let size2: f64 = 0.2;
+            let r2: f64 = size2 / 2.0;
+            let c2 = Vector2::new(0.7 + r2, 0.5 + r2);
+            let positions2 = sampling::poisson_disk(Vector2::new(0.7, 0.5), Vector2::new(0.7 + size2, 0.5 + size2), particle_diam, |p| (p - c2).norm() < r2, &mut rng);
+            let mass2 = density * PI * r2 * r2 / positions2.len() as f64;
+            for pos in positions2 {
+                let particle = Particle::new(pos, Vector2::new(-5.0, 0.0), mass2, snow_temperature);
+                grid.add_particle(particle);
+            }
+            grid
         }
-    }
-
-    let size2: f64 = 0.2;
-    let r2: f64 = size2 / 2.0;
-    let a2: f64 = size2 * size2;
-    let pc2: usize = (a2 / particle_area) as usize;
-    for _ in 0..pc2 {
-        let pos = Vector2::new(0.7 + size2 * rng.gen::<f64>(), 0.5 + size2 * rng.gen::<f64>());
-        if (pos - Vector2::new(0.7 + r2, 0.5 + r2)).norm() < r2 {
-            let particle = Particle::new(pos, Vector2::new(-5.0, 0.0), particle_mass);
-            grid.add_particle(particle);
+        Scene::Volumes => {
+            // Snow sticks to the walls and its hardening is capped so the compacted base of the slab stays soft enough to slide
+            params.wall_contact = Contact::new(BoundaryCondition::Sticky, 0.0, 0.0);
+            params.hardening_law = HardeningLaw::CappedExponential { min_factor: 0.5, max_factor: 3.0 };
+            let mut grid = Grid::new(64, params);
+
+            // The slope is frictionless, so only the strength of the slab keeps it in place
+            let slope = Shape::Segment { a: Vector2::new(0.05, 0.35), b: Vector2::new(0.6, 0.35 + 0.55 * (PI / 6.0).tan()) };
+            grid.add_collider(Collider::new(slope, Contact::new(BoundaryCondition::Slip, 0.0, 0.0), Vector2::zeros(), collider_color));
+            let slab_center = Vector2::new(0.25, 0.43);
+            let slab = Volume::Slab { thickness: 0.05 }.transformed(slab_center, PI / 6.0, 1.0);
+            grid.add_volume(&slab.intersection(Volume::Box { half_extents: Vector2::repeat(0.12) }.translated(slab_center)), Vector2::zeros(), Some(&heterogeneity));
+
+            let dome = Volume::Ellipse { radii: Vector2::new(0.2, 0.15) }.intersection(Volume::Box { half_extents: Vector2::new(0.2, 0.075) }.translated(Vector2::new(0.0, -0.075)));
+            let hollow = Volume::Ellipse { radii: Vector2::new(0.16, 0.11) }.union(Volume::Box { half_extents: Vector2::new(0.03, 0.04) }.translated(Vector2::new(-0.17, -0.04)));
+            grid.add_volume(&dome.difference(hollow).transformed(Vector2::new(0.78, 0.95), 0.0, 0.6), Vector2::zeros(), Some(&heterogeneity));
+
+            // A cornice against the wall above the slope, a pile and a ring on the floor below it, and a snowman and a log falling in
+            let cornice = Volume::Polygon(vec![Vector2::new(0.03, 0.2), Vector2::new(0.1, 0.32), Vector2::new(0.03, 0.32)]);
+            let pile = Volume::Cone { half_height: 0.04, radius: 0.08 }.translated(Vector2::new(0.15, 0.91));
+            let ring = Volume::Torus { major_radius: 0.05, minor_radius: 0.02 }.translated(Vector2::new(0.42, 0.9));
+            let snowman = Volume::Circle { radius: 0.04 }.union(Volume::Circle { radius: 0.025 }.translated(Vector2::new(0.0, -0.06))).translated(Vector2::new(0.6, 0.15));
+            let log = Volume::Cylinder { half_height: 0.06, radius: 0.015 }.rotated(PI / 4.0).translated(Vector2::new(0.8, 0.2));
+            for volume in [cornice, pile, ring, snowman, log] {
+                grid.add_volume(&volume, Vector2::zeros(), Some(&heterogeneity));
+            }
+            grid
         }
-    }
-
-    // for pos in positions.iter() {
-    //     let position = pos.clone();
-    //     let particle = Particle::new(position, Vector2::new(-5.0, 0.0), particle_mass);
-    //     grid.add_particle(particle);
-    // }
+        Scene::Mask => {
+            // Hardening read from a measured curve, softer than the exponential under strong compaction
+            let table = HardeningTable::new(vec![(0.6, 4.0), (0.9, 1.8), (1.0, 1.0), (1.1, 0.6)]).unwrap();
+            params.hardening_law = HardeningLaw::Table(table);
+            let water_temperature = 2.0;
+            let mut grid = Grid::new(64, params);
+
+            // Black pixels are packed snow, grey ones water
+            let mask = ImageMask::load("assets/mask.png", Vector2::new(0.25, 0.5), Vector2::new(0.75, 1.0), density, snow_temperature)
+                .expect("failed to load mask")
+                .with_layers(vec![
+                    MaskLayer::new(Color::new(0.0, 0.0, 0.0, 1.0), 2.0 * density, snow_temperature).with_material(2.0, 2.0),
+                    MaskLayer::new(Color::new(0.5, 0.5, 0.5, 1.0), 10.0 * density, water_temperature).with_phase(Phase::Water),
+                ])
+                .with_tolerance(0.2);
+            grid.add_image_mask(&mask, Vector2::zeros(), Some(&heterogeneity));
+            grid
+        }
+        Scene::Snowfall => {
+            // Fresh snow hardens more slowly, and bounces a little off the walls
+            params.hardening_law = HardeningLaw::PowerLaw;
+            params.wall_contact = Contact::new(BoundaryCondition::Restitution(0.3), 0.3, 0.2);
+            let params = params.with_domain([DomainBoundary::Periodic, DomainBoundary::Periodic, DomainBoundary::Open, DomainBoundary::Wall]);
+            let mut grid = Grid::new(64, params);
+
+            let mass = density * particle_diam * particle_diam;
+            grid.add_emitter(Emitter::new(Region::new(Vector2::new(0.1, 0.05), Vector2::new(0.8, 0.08)), 5000.0, Vector2::new(0.0, 1.0), mass, snow_temperature).with_window(0.0, Some(0.5)));
+            grid.add_sink(Region::new(Vector2::new(0.85, 0.8), Vector2::new(0.95, 0.95)));
+            grid.add_collider(Collider::new(Shape::Circle { center: Vector2::new(0.45, 0.7), radius: 0.08 }, contact, Vector2::zeros(), collider_color));
+            grid.add_collider(Collider::new(Shape::Polygon(vec![Vector2::new(0.6, 0.95), Vector2::new(0.7, 0.8), Vector2::new(0.8, 0.95)]), contact, Vector2::zeros(), collider_color));
+            grid
+        }
+    };
 
     grid.p2g_mass();
     grid.calculate_volumes();
+    grid
+}
+
+#[macroquad::main("Snow simulation")]
+async fn main() {
+    request_new_screen_size(1000.0, 1000.0);
+
+    let mut grid = build_scene(Scene::Snowballs);
 
     let print_time_taken = |start: Instant, name: &str| {
         // println!("{}: {} ms", name, start.elapsed().as_millis());
@@ -66,6 +158,11 @@ async fn main() {
         if is_key_pressed(KeyCode::Space) {
             sim = !sim;
         }
+        let scenes = [(KeyCode::Key1, Scene::Snowballs), (KeyCode::Key2, Scene::Volumes), (KeyCode::Key3, Scene::Mask), (KeyCode::Key4, Scene::Snowfall)];
+        if let Some(&(_, scene)) = scenes.iter().find(|(key, _)| is_key_pressed(*key)) {
+            grid = build_scene(scene);
+            sim = false;
+        }
         if !sim {
             let frame = next_frame().await;
             continue;
@@ -75,6 +172,7 @@ async fn main() {
 
         // Calculate time taken
         let start = Instant::now();
+        grid.emit_particles();
         grid.reset_parameters();
         print_time_taken(start, "Reset parameters");
 
@@ -94,6 +192,11 @@ async fn main() {
         grid.compute_grid_forces();
         print_time_taken(start, "Compute grid forces");
 
+        let start = Instant::now();
+        grid.p2g_heat();
+        grid.compute_heat_diffusion();
+        print_time_taken(start, "Heat diffusion");
+
         let start = Instant::now();
         grid.update_grid_velocities();
         print_time_taken(start, "Update grid velocities");
@@ -110,8 +213,18 @@ async fn main() {
         grid.update_velocity();
         print_time_taken(start, "Update velocity");
 
+        let start = Instant::now();
+        grid.collision_particles();
+        grid.update_colliders();
+        print_time_taken(start, "Collision particles");
+
+        let start = Instant::now();
+        grid.update_temperatures();
+        print_time_taken(start, "Update temperatures");
+
         let start = Instant::now();
         grid.update_particle_positions();
+        grid.enforce_domain();
         print_time_taken(start, "Update particle positions");
 
         let start = Instant::now();
@@ -119,6 +232,9 @@ async fn main() {
         for particle in &grid.particles {
             particle.draw();
         }
+        for collider in &grid.colliders {
+            collider.draw();
+        }
         draw_text("Particle mass view", 10.0, 20.0, 30.0, Color::new(0.0, 1.0, 0.0, 1.0));
         draw_text("Grid mass view", screen_width() / 2.0 + 10.0, 20.0, 30.0, Color::new(0.0, 1.0, 0.0, 1.0));
         draw_text("Particle velocity view", 10.0, screen_height() / 2.0 + 20.0, 30.0, Color::new(0.0, 1.0, 0.0, 1.0));
@@ -129,4 +245,4 @@ async fn main() {
         let frame = next_frame().await;
         frame
     }
-}
+}
\ No newline at end of file
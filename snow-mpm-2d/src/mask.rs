@@ -0,0 +1,149 @@
+use std::io;
+use std::path::Path;
+use macroquad::prelude::{BLACK, Color, Image};
+use nalgebra::Vector2;
+use crate::particle::Phase;
+
+/// What the pixels of one colour in a mask image are filled with.
+#[derive(Debug, Clone, Copy)]
+pub struct MaskLayer {
+    pub color: Color,
+    pub density: f64,
+    pub temperature: f64,
+    pub phase: Phase,
+    /// Multipliers on the Young's modulus and the critical compression and stretch, like `Heterogeneity` applies.
+    pub stiffness: f64,
+    pub strength: f64,
+}
+
+impl MaskLayer {
+    pub fn new(color: Color, density: f64, temperature: f64) -> Self {
+        MaskLayer {
+            color,
+            density,
+            temperature,
+            phase: Phase::Snow,
+            stiffness: 1.0,
+            strength: 1.0,
+        }
+    }
+
+    pub fn with_phase(mut self, phase: Phase) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    pub fn with_material(mut self, stiffness: f64, strength: f64) -> Self {
+        self.stiffness = stiffness;
+        self.strength = strength;
+        self
+    }
+}
+
+/// An image stretched over the rectangle `[min, max]` of the simulation domain, with its top row at `min.y`.
+/// Opaque pixels within `tolerance` of a layer's colour are filled with that layer, the closest one if several match.
+pub struct ImageMask {
+    image: Image,
+    pub min: Vector2<f64>,
+    pub max: Vector2<f64>,
+    pub layers: Vec<MaskLayer>,
+    pub tolerance: f64,
+}
+
+impl ImageMask {
+    /// Loads a PNG with a single layer that fills dark pixels with snow of `density` and `temperature`.
+    pub fn load<P: AsRef<Path>>(path: P, min: Vector2<f64>, max: Vector2<f64>, density: f64, temperature: f64) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let image = Image::from_file_with_format(&bytes, None).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(ImageMask {
+            image,
+            min,
+            max,
+            layers: vec![MaskLayer::new(BLACK, density, temperature)],
+            tolerance: 0.5,
+        })
+    }
+
+    /// Replaces the default layer, e.g. to map several colours to different densities or to water.
+    pub fn with_layers(mut self, layers: Vec<MaskLayer>) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    pub fn with_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    fn pixel_size(&self) -> Vector2<f64> {
+        (self.max - self.min).component_div(&Vector2::new(self.image.width() as f64, self.image.height() as f64))
+    }
+
+    /// Index of the layer the pixel under `p` is filled with, if any.
+    pub fn layer_at(&self, p: Vector2<f64>) -> Option<usize> {
+        let pixel = (p - self.min).component_div(&self.pixel_size());
+        if pixel.x < 0.0 || pixel.y < 0.0 || pixel.x >= self.image.width() as f64 || pixel.y >= self.image.height() as f64 {
+            return None;
+        }
+        let color = self.image.get_pixel(pixel.x as u32, pixel.y as u32);
+        if color.a < 0.5 {
+            return None;
+        }
+
+        let distance = |layer: &MaskLayer| {
+            let (dr, dg, db) = (color.r - layer.color.r, color.g - layer.color.g, color.b - layer.color.b);
+            ((dr * dr + dg * dg + db * db) as f64).sqrt()
+        };
+        self.layers.iter()
+            .enumerate()
+            .map(|(index, layer)| (index, distance(layer)))
+            .filter(|&(_, d)| d <= self.tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(index, _)| index)
+    }
+
+    /// Area covered by the pixels of layer `index` within the box `[min, max]`, e.g. the part of the mask that lies
+    /// inside the domain.
+    pub fn area(&self, index: usize, min: Vector2<f64>, max: Vector2<f64>) -> f64 {
+        let pixel_size = self.pixel_size();
+        let mut area = 0.0;
+        for x in 0..self.image.width() {
+            for y in 0..self.image.height() {
+                let corner = self.min + Vector2::new(x as f64, y as f64).component_mul(&pixel_size);
+                if self.layer_at(corner + pixel_size / 2.0) == Some(index) {
+                    let overlap = ((corner + pixel_size).inf(&max) - corner.sup(&min)).map(|c| c.max(0.0));
+                    area += overlap.x * overlap.y;
+                }
+            }
+        }
+        area
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::Grid;
+    use crate::params::Params;
+    use super::*;
+
+    #[test]
+    fn mask_overhanging_the_domain_only_counts_the_inside() {
+        // A black mask whose lower right quarter lies inside the domain
+        let mask = ImageMask {
+            image: Image::gen_image_color(8, 8, BLACK),
+            min: Vector2::new(0.5, 0.5),
+            max: Vector2::new(1.5, 1.5),
+            layers: vec![MaskLayer::new(BLACK, 100.0, -5.0)],
+            tolerance: 0.5,
+        };
+        assert!((mask.area(0, mask.min, mask.max) - 1.0).abs() < 1e-12);
+        assert!((mask.area(0, Vector2::zeros(), Vector2::repeat(1.0)) - 0.25).abs() < 1e-12);
+
+        let params = Params::new();
+        let particle_diam = params.particle_diam;
+        let mut grid = Grid::new(16, Params { particle_diam: 10.0 * particle_diam, ..params });
+        grid.add_image_mask(&mask, Vector2::zeros(), None);
+        let mass: f64 = grid.particles.iter().map(|p| p.mass).sum();
+        assert!((mass - 100.0 * 0.25).abs() < 1e-9);
+    }
+}
\ No newline at end of file
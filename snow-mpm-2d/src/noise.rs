@@ -0,0 +1,97 @@
+use nalgebra::Vector2;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use crate::particle::Particle;
+
+/// Seeded 2D Perlin gradient noise, values roughly in `[-1, 1]`.
+pub struct Noise {
+    perm: [usize; 512],
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut table: Vec<usize> = (0..256).collect();
+        table.shuffle(&mut rng);
+
+        let mut perm = [0; 512];
+        for i in 0..512 {
+            perm[i] = table[i % 256];
+        }
+        Noise { perm }
+    }
+
+    fn fade(t: f64) -> f64 {
+        t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+    }
+
+    fn lerp(t: f64, a: f64, b: f64) -> f64 {
+        a + t * (b - a)
+    }
+
+    fn gradient(hash: usize, x: f64, y: f64) -> f64 {
+        match hash & 7 {
+            0 => x + y,
+            1 => -x + y,
+            2 => x - y,
+            3 => -x - y,
+            4 => x,
+            5 => -x,
+            6 => y,
+            _ => -y,
+        }
+    }
+
+    pub fn perlin(&self, pos: Vector2<f64>) -> f64 {
+        let xi = (pos.x.floor() as i64 & 255) as usize;
+        let yi = (pos.y.floor() as i64 & 255) as usize;
+        let x = pos.x - pos.x.floor();
+        let y = pos.y - pos.y.floor();
+        let u = Self::fade(x);
+        let v = Self::fade(y);
+
+        let p = &self.perm;
+        let aa = p[p[xi] + yi];
+        let ab = p[p[xi] + yi + 1];
+        let ba = p[p[xi + 1] + yi];
+        let bb = p[p[xi + 1] + yi + 1];
+
+        Self::lerp(v,
+                   Self::lerp(u, Self::gradient(aa, x, y), Self::gradient(ba, x - 1.0, y)),
+                   Self::lerp(u, Self::gradient(ab, x, y - 1.0), Self::gradient(bb, x - 1.0, y - 1.0)))
+    }
+}
+
+/// Per-body variation of snow properties. Amplitudes are relative, e.g. `0.2` varies a property by about ±20%.
+pub struct Heterogeneity {
+    noise: Noise,
+    pub frequency: f64,
+    pub density_amplitude: f64,
+    pub youngs_modulus_amplitude: f64,
+    pub critical_amplitude: f64,
+}
+
+impl Heterogeneity {
+    pub fn new(seed: u64, frequency: f64, density_amplitude: f64, youngs_modulus_amplitude: f64, critical_amplitude: f64) -> Self {
+        Heterogeneity {
+            noise: Noise::new(seed),
+            frequency,
+            density_amplitude,
+            youngs_modulus_amplitude,
+            critical_amplitude,
+        }
+    }
+
+    fn factor(&self, pos: Vector2<f64>, amplitude: f64) -> f64 {
+        (1.0 + amplitude * self.noise.perlin(pos)).max(0.05)
+    }
+
+    pub fn apply(&self, p: &mut Particle) {
+        // Offset the samples so the three properties vary independently
+        let pos = p.pos * self.frequency;
+        p.mass *= self.factor(pos, self.density_amplitude);
+        p.stiffness *= self.factor(pos + Vector2::new(31.7, 11.3), self.youngs_modulus_amplitude);
+        p.strength *= self.factor(pos + Vector2::new(73.9, 41.5), self.critical_amplitude);
+    }
+}
\ No newline at end of file
@@ -1,8 +1,132 @@
 use nalgebra::Vector2;
 
+#[derive(Debug, Clone)]
+pub enum HardeningLaw {
+    /// `exp(xi * (1 - J_p))`, Stomakhin et al. 2013.
+    Exponential,
+    /// Exponential hardening clamped to `[min_factor, max_factor]`.
+    CappedExponential { min_factor: f64, max_factor: f64 },
+    /// `J_p^(-xi)`.
+    PowerLaw,
+    /// Piecewise linear factor over a table of `(J_p, factor)` pairs, held constant outside the table.
+    Table(HardeningTable),
+}
+
+#[derive(Debug, Clone)]
+pub struct HardeningTable {
+    points: Vec<(f64, f64)>,
+}
+
+impl HardeningTable {
+    /// `None` unless there is at least one point and the `J_p` values are strictly increasing.
+    pub fn new(points: Vec<(f64, f64)>) -> Option<Self> {
+        if points.is_empty() || points.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return None;
+        }
+        Some(HardeningTable { points })
+    }
+
+    pub fn factor(&self, jp: f64) -> f64 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        if jp <= first.0 {
+            return first.1;
+        }
+        for pair in self.points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if jp <= b.0 {
+                let t = (jp - a.0) / (b.0 - a.0);
+                return a.1 + t * (b.1 - a.1);
+            }
+        }
+        last.1
+    }
+}
+
+impl HardeningLaw {
+    pub fn factor(&self, xi: f64, jp: f64) -> f64 {
+        match self {
+            HardeningLaw::Exponential => (xi * (1.0 - jp)).exp(),
+            HardeningLaw::CappedExponential { min_factor, max_factor } => (xi * (1.0 - jp)).exp().clamp(*min_factor, *max_factor),
+            HardeningLaw::PowerLaw => jp.powf(-xi),
+            HardeningLaw::Table(table) => table.factor(jp),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoundaryCondition {
+    /// No-slip: the snow moves with the collider.
+    Sticky,
+    /// Frictionless: the normal velocity is removed, the tangential velocity is kept.
+    Slip,
+    /// The normal velocity is removed and the tangential velocity is scaled by the factor, whichever way the snow
+    /// moves. This is how the walls of the 2D domain originally behaved.
+    Damped(f64),
+    /// Coulomb friction while approaching, snow moving away is left free.
+    Separating,
+    /// Like `Separating`, but the approaching normal velocity bounces back scaled by the coefficient.
+    Restitution(f64),
+}
+
+/// How a collider treats the snow touching it.
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub boundary: BoundaryCondition,
+    /// Tangential slip below `static_friction` times the normal velocity is stopped completely.
+    pub static_friction: f64,
+    /// Faster slip is slowed by `dynamic_friction` times the normal velocity.
+    pub dynamic_friction: f64,
+}
+
+impl Contact {
+    pub fn new(boundary: BoundaryCondition, static_friction: f64, dynamic_friction: f64) -> Self {
+        Contact {
+            boundary,
+            static_friction,
+            dynamic_friction,
+        }
+    }
+
+    /// Velocity relative to the collider after contact, `normal` pointing out of the collider.
+    pub fn respond(&self, vel_rel: Vector2<f64>, normal: Vector2<f64>) -> Vector2<f64> {
+        let v_n = vel_rel.dot(&normal);
+        let velocity_tangent = vel_rel - normal * v_n;
+        let bounce = match self.boundary {
+            BoundaryCondition::Sticky => return Vector2::zeros(),
+            BoundaryCondition::Slip => return velocity_tangent,
+            BoundaryCondition::Damped(factor) => return factor * velocity_tangent,
+            _ if v_n >= 0.0 => return vel_rel,
+            BoundaryCondition::Restitution(restitution) => -restitution * v_n * normal,
+            BoundaryCondition::Separating => Vector2::zeros(),
+        };
+
+        let mag_velocity_tangent = velocity_tangent.norm();
+        if mag_velocity_tangent <= -self.static_friction * v_n {
+            bounce
+        } else {
+            bounce + (1.0 + self.dynamic_friction * v_n / mag_velocity_tangent).max(0.0) * velocity_tangent
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DomainBoundary {
+    /// Solid wall using `wall_contact`.
+    Wall,
+    /// Particles that leave through the face are deleted.
+    Open,
+    /// Particles and grid transfers wrap around to the opposite face, which must be periodic too.
+    Periodic,
+}
+
 #[derive(Debug)]
 pub struct Params {
     pub hardening_coefficient: f64,
+    pub hardening_law: HardeningLaw,
+    pub relaxation_time: Option<f64>,
+    /// Share of the FLIP update in the particle velocities and temperatures, the rest is PIC.
+    pub flip_pic_ratio: f64,
     pub critical_compression: f64,
     pub critical_stretch: f64,
     pub mu_0: f64,
@@ -13,8 +137,17 @@ pub struct Params {
     pub particle_diam: f64,
     pub density: f64,
     pub gravity: Vector2<f64>,
-    pub particle_area: f64,
-    pub particle_mass: f64,
+    pub melting_point: f64,
+    pub latent_heat: f64,
+    pub heat_capacity_snow: f64,
+    pub heat_capacity_water: f64,
+    pub conductivity_snow: f64,
+    pub conductivity_water: f64,
+    pub snow_temperature: f64,
+    pub wall_temperature: Option<f64>,
+    pub wall_contact: Contact,
+    /// Faces of the unit square in the order -x, +x, -y, +y. Note that +y is the floor.
+    pub domain: [DomainBoundary; 4],
 }
 
 impl Params {
@@ -24,6 +157,10 @@ impl Params {
         let hardening_coefficient: f64 = 5.0;
         let critical_compression: f64 = 1.0 - 1.9e-2;
         let critical_stretch: f64 = 1.0 + 7.5e-3;
+        let hardening_law = HardeningLaw::Exponential;
+        // Viscoplastic relaxation time, None projects onto the yield surface instantly
+        let relaxation_time: Option<f64> = None;
+        let flip_pic_ratio: f64 = 0.95;
 
         let mu_0 = young_modulus / (2.0 * (2.0 + poisson_ration));
         let lambda_0 = young_modulus * poisson_ration / ((1.0 + poisson_ration) * (1.0 - 2.0 * poisson_ration));
@@ -34,11 +171,26 @@ impl Params {
         let particle_diam: f64 = 0.002;
         let density: f64 = 100.0;
         let gravity: Vector2<f64> = Vector2::new(0.0, 9.81);
-        let particle_area: f64 = particle_diam * particle_diam;
-        let particle_mass: f64 = density * particle_area;
+
+        let melting_point: f64 = 0.0;
+        let latent_heat: f64 = 3.34e5;
+        let heat_capacity_snow: f64 = 2.09e3;
+        let heat_capacity_water: f64 = 4.18e3;
+        let conductivity_snow: f64 = 0.3;
+        let conductivity_water: f64 = 0.6;
+        let snow_temperature: f64 = -5.0;
+        let wall_temperature: Option<f64> = None;
+
+        let wall_contact = Contact::new(BoundaryCondition::Damped(0.9), 0.0, 0.0);
+        // let wall_contact = Contact::new(BoundaryCondition::Separating, 0.3, 0.2);
+        // let wall_contact = Contact::new(BoundaryCondition::Sticky, 0.0, 0.0);
+        let domain = [DomainBoundary::Wall; 4];
 
         Params {
             hardening_coefficient,
+            hardening_law,
+            relaxation_time,
+            flip_pic_ratio,
             critical_compression,
             critical_stretch,
             mu_0,
@@ -49,8 +201,54 @@ impl Params {
             particle_diam,
             density,
             gravity,
-            particle_area,
-            particle_mass,
+            melting_point,
+            latent_heat,
+            heat_capacity_snow,
+            heat_capacity_water,
+            conductivity_snow,
+            conductivity_water,
+            snow_temperature,
+            wall_temperature,
+            wall_contact,
+            domain,
         }
     }
+
+    /// Faces in the order -x, +x, -y, +y.
+    pub fn with_domain(mut self, domain: [DomainBoundary; 4]) -> Self {
+        if (domain[0] == DomainBoundary::Periodic) != (domain[1] == DomainBoundary::Periodic)
+            || (domain[2] == DomainBoundary::Periodic) != (domain[3] == DomainBoundary::Periodic) {
+            panic!("periodic boundaries must be set on both faces of an axis");
+        }
+        self.domain = domain;
+        self
+    }
+
+    /// Whether the x and y axes wrap around.
+    pub fn periodic(&self) -> [bool; 2] {
+        [self.domain[0] == DomainBoundary::Periodic, self.domain[2] == DomainBoundary::Periodic]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_wall_stops_the_normal_velocity_and_damps_the_tangential() {
+        let params = Params::new();
+        let left_wall = Vector2::new(1.0, 0.0);
+        assert_eq!(params.wall_contact.respond(Vector2::new(-2.0, 1.0), left_wall), Vector2::new(0.0, 0.9));
+        assert_eq!(params.wall_contact.respond(Vector2::new(2.0, 1.0), left_wall), Vector2::new(0.0, 0.9));
+    }
+
+    #[test]
+    fn separating_contact_releases_snow_moving_away() {
+        let contact = Contact::new(BoundaryCondition::Separating, 0.3, 0.2);
+        let floor = Vector2::new(0.0, -1.0);
+        assert_eq!(contact.respond(Vector2::new(1.0, -1.0), floor), Vector2::new(1.0, -1.0));
+        assert_eq!(contact.respond(Vector2::new(0.1, 1.0), floor), Vector2::zeros());
+        let sliding = contact.respond(Vector2::new(1.0, 1.0), floor);
+        assert!((sliding - Vector2::new(0.8, 0.0)).norm() < 1e-12);
+    }
 }
\ No newline at end of file
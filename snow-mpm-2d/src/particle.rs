@@ -1,6 +1,12 @@
 use macroquad::prelude::{Color, draw_circle, draw_line, screen_height, screen_width};
 use nalgebra::{Matrix2, Matrix4, Vector2};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    Snow,
+    Water,
+}
+
 pub struct Particle {
     pub vol: f64,
     pub mass: f64,
@@ -14,10 +20,17 @@ pub struct Particle {
     pub w_d_y: Matrix4<f64>,
     pub w: Matrix4<f64>,
     pub vel_d: Matrix2<f64>,
+
+    pub temperature: f64,
+    pub latent_heat: f64,
+    pub phase: Phase,
+
+    pub stiffness: f64,
+    pub strength: f64,
 }
 
 impl Particle {
-    pub fn new(pos: Vector2<f64>, vel: Vector2<f64>, mass: f64) -> Self {
+    pub fn new(pos: Vector2<f64>, vel: Vector2<f64>, mass: f64, temperature: f64) -> Self {
         Particle {
             vol: 0.0,
             mass,
@@ -31,6 +44,13 @@ impl Particle {
             w_d_y: Matrix4::zeros(),
             w: Matrix4::zeros(),
             vel_d: Matrix2::zeros(),
+
+            temperature,
+            latent_heat: 0.0,
+            phase: Phase::Snow,
+
+            stiffness: 1.0,
+            strength: 1.0,
         }
     }
 
@@ -42,6 +62,7 @@ impl Particle {
         let color = Color::new(density / 100.0, density / 100.0, density / 100.0, 1.0);
         // cap color at 0.5
         let color = if color.r < 0.95 { Color::new(0.95, 0.95, 0.95, 1.0) } else { color };
+        let color = if self.phase == Phase::Water { Color::new(0.25, 0.5, 1.0, 1.0) } else { color };
         draw_circle(x, y, 3.0, color);
 
         let x = (self.pos.x as f32) * screen_width() / 2.0;
@@ -0,0 +1,98 @@
+use nalgebra::Vector2;
+use rand::Rng;
+
+/// Blue-noise samples inside the shape given by `inside` within the box `[min, max]`, no two closer than
+/// `radius` (Bridson 2007). Random seeds start new patches so disconnected shapes work. Seeding gives up after as
+/// many misses in a row as the background grid has cells, which leaves an uncovered gap the size of one cell with
+/// probability below `1/e` and larger gaps practically never.
+/// An empty box, i.e. `min` above `max` along some axis, has no samples.
+pub fn poisson_disk(min: Vector2<f64>, max: Vector2<f64>, radius: f64, inside: impl Fn(Vector2<f64>) -> bool, rng: &mut impl Rng) -> Vec<Vector2<f64>> {
+    if is_empty(min, max) {
+        return Vec::new();
+    }
+
+    let attempts = 30;
+    let cell_size = radius / 2f64.sqrt();
+    let dims = ((max - min) / cell_size).map(|c| c.ceil().max(1.0) as usize);
+    let mut cells: Vec<Option<usize>> = vec![None; dims.x * dims.y];
+    let cell_of = |p: Vector2<f64>| {
+        let c = ((p - min) / cell_size).map(|c| c.floor().max(0.0) as usize);
+        Vector2::new(c.x.min(dims.x - 1), c.y.min(dims.y - 1))
+    };
+    let flat = |c: Vector2<usize>| c.x + dims.x * c.y;
+
+    let mut samples: Vec<Vector2<f64>> = Vec::new();
+    let mut active = Vec::new();
+    let fits = |p: Vector2<f64>, samples: &[Vector2<f64>], cells: &[Option<usize>]| {
+        let c = cell_of(p);
+        for i in c.x.saturating_sub(2)..(c.x + 3).min(dims.x) {
+            for j in c.y.saturating_sub(2)..(c.y + 3).min(dims.y) {
+                if let Some(index) = cells[flat(Vector2::new(i, j))] {
+                    if (samples[index] - p).norm_squared() < radius * radius {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    };
+
+    let seed_attempts = (dims.x * dims.y).max(attempts);
+    let mut failed_seeds = 0;
+    while failed_seeds < seed_attempts {
+        let seed = Vector2::new(rng.gen_range(min.x..=max.x), rng.gen_range(min.y..=max.y));
+        if !inside(seed) || !fits(seed, &samples, &cells) {
+            failed_seeds += 1;
+            continue;
+        }
+        failed_seeds = 0;
+        cells[flat(cell_of(seed))] = Some(samples.len());
+        active.push(samples.len());
+        samples.push(seed);
+
+        while let Some(&current) = active.last() {
+            let center = samples[current];
+            let mut found = false;
+            for _ in 0..attempts {
+                // Random point in the ring between radius and 2 * radius
+                let angle = rng.gen_range(0.0..std::f64::consts::TAU);
+                let candidate = center + Vector2::new(angle.cos(), angle.sin()) * radius * rng.gen_range(1.0..2.0);
+                let in_box = candidate.x >= min.x && candidate.x <= max.x && candidate.y >= min.y && candidate.y <= max.y;
+                if in_box && inside(candidate) && fits(candidate, &samples, &cells) {
+                    cells[flat(cell_of(candidate))] = Some(samples.len());
+                    active.push(samples.len());
+                    samples.push(candidate);
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                active.pop();
+            }
+        }
+    }
+    samples
+}
+
+/// Area of the shape given by `inside` within `[min, max]`, counted on a lattice with spacing `step`.
+pub fn estimate_area(min: Vector2<f64>, max: Vector2<f64>, step: f64, inside: impl Fn(Vector2<f64>) -> bool) -> f64 {
+    if is_empty(min, max) {
+        return 0.0;
+    }
+
+    let dims = ((max - min) / step).map(|c| c.ceil().max(1.0) as usize);
+    let mut count = 0;
+    for i in 0..dims.x {
+        for j in 0..dims.y {
+            if inside(min + (Vector2::new(i, j).cast::<f64>() + Vector2::repeat(0.5)) * step) {
+                count += 1;
+            }
+        }
+    }
+    count as f64 * step * step
+}
+
+/// Whether the box `[min, max]` is empty along some axis.
+pub fn is_empty(min: Vector2<f64>, max: Vector2<f64>) -> bool {
+    min.iter().zip(max.iter()).any(|(lo, hi)| lo > hi)
+}
\ No newline at end of file
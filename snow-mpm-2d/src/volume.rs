@@ -0,0 +1,146 @@
+use nalgebra::{Rotation2, Vector2};
+use crate::collider::polygon_distance;
+
+/// Solid areas for the initial snow bodies, built from primitives centred on the origin and combined with
+/// constructive solid geometry. These are the cross-sections of the 3D volumes, with y pointing down.
+#[derive(Debug, Clone)]
+pub enum Volume {
+    Circle { radius: f64 },
+    Box { half_extents: Vector2<f64> },
+    /// Section through the axis of an upright cylinder, a box of `radius` by `half_height`.
+    Cylinder { half_height: f64, radius: f64 },
+    /// Triangle with its base of `radius` at `y = half_height` and its apex at `y = -half_height`.
+    Cone { half_height: f64, radius: f64 },
+    /// Section through the axis of a torus, two discs of `minor_radius` either side of the centre.
+    Torus { major_radius: f64, minor_radius: f64 },
+    Ellipse { radii: Vector2<f64> },
+    /// Band between `y = -thickness / 2` and `y = thickness / 2`, unbounded in x. Filling it covers the whole
+    /// domain, so tilt it for a slab on a slope or intersect it with another volume.
+    Slab { thickness: f64 },
+    Polygon(Vec<Vector2<f64>>),
+    Union(Box<Volume>, Box<Volume>),
+    Intersection(Box<Volume>, Box<Volume>),
+    /// The first volume with the second one cut out of it.
+    Difference(Box<Volume>, Box<Volume>),
+    /// Scaled, then rotated by `angle` radians, then translated.
+    Transformed { volume: Box<Volume>, translation: Vector2<f64>, angle: f64, scale: f64 },
+}
+
+impl Volume {
+    pub fn union(self, other: Volume) -> Volume {
+        Volume::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: Volume) -> Volume {
+        Volume::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn difference(self, other: Volume) -> Volume {
+        Volume::Difference(Box::new(self), Box::new(other))
+    }
+
+    pub fn transformed(self, translation: Vector2<f64>, angle: f64, scale: f64) -> Volume {
+        Volume::Transformed {
+            volume: Box::new(self),
+            translation,
+            angle,
+            scale,
+        }
+    }
+
+    pub fn translated(self, translation: Vector2<f64>) -> Volume {
+        self.transformed(translation, 0.0, 1.0)
+    }
+
+    pub fn rotated(self, angle: f64) -> Volume {
+        self.transformed(Vector2::zeros(), angle, 1.0)
+    }
+
+    /// Signed distance to the boundary, negative inside. Exact for the primitives except the ellipse, and a
+    /// bound for the CSG operations, which is all filling a volume needs.
+    pub fn signed_distance(&self, p: Vector2<f64>) -> f64 {
+        match self {
+            Volume::Circle { radius } => p.norm() - radius,
+            Volume::Box { half_extents } => {
+                let q = p.abs() - half_extents;
+                q.sup(&Vector2::zeros()).norm() + q.x.max(q.y).min(0.0)
+            }
+            Volume::Cylinder { half_height, radius } => Volume::Box { half_extents: Vector2::new(*radius, *half_height) }.signed_distance(p),
+            Volume::Cone { half_height, radius } => polygon_distance(p, &[
+                Vector2::new(-radius, *half_height),
+                Vector2::new(*radius, *half_height),
+                Vector2::new(0.0, -half_height),
+            ]),
+            Volume::Torus { major_radius, minor_radius } => (Vector2::new(p.x.abs() - major_radius, p.y)).norm() - minor_radius,
+            Volume::Ellipse { radii } => {
+                let k0 = p.component_div(radii).norm();
+                let k1 = p.component_div(&radii.component_mul(radii)).norm();
+                if k1 > 0.0 { k0 * (k0 - 1.0) / k1 } else { -radii.min() }
+            }
+            Volume::Slab { thickness } => p.y.abs() - thickness / 2.0,
+            Volume::Polygon(vertices) => polygon_distance(p, vertices),
+            Volume::Union(a, b) => a.signed_distance(p).min(b.signed_distance(p)),
+            Volume::Intersection(a, b) => a.signed_distance(p).max(b.signed_distance(p)),
+            Volume::Difference(a, b) => a.signed_distance(p).max(-b.signed_distance(p)),
+            Volume::Transformed { volume, translation, angle, scale } => {
+                volume.signed_distance(Rotation2::new(-angle) * (p - translation) / *scale) * scale
+            }
+        }
+    }
+
+    pub fn contains(&self, p: Vector2<f64>) -> bool {
+        self.signed_distance(p) <= 0.0
+    }
+
+    /// Axis-aligned bounding box, infinite along the axes a slab extends to.
+    pub fn bounds(&self) -> (Vector2<f64>, Vector2<f64>) {
+        let centred = |half: Vector2<f64>| (-half, half);
+        match self {
+            Volume::Circle { radius } => centred(Vector2::repeat(*radius)),
+            Volume::Box { half_extents } => centred(*half_extents),
+            Volume::Cylinder { half_height, radius } | Volume::Cone { half_height, radius } => centred(Vector2::new(*radius, *half_height)),
+            Volume::Torus { major_radius, minor_radius } => centred(Vector2::new(major_radius + minor_radius, *minor_radius)),
+            Volume::Ellipse { radii } => centred(*radii),
+            Volume::Slab { thickness } => centred(Vector2::new(f64::INFINITY, thickness / 2.0)),
+            Volume::Polygon(vertices) => vertices.iter().fold(
+                (Vector2::repeat(f64::INFINITY), Vector2::repeat(f64::NEG_INFINITY)),
+                |(min, max), v| (min.inf(v), max.sup(v)),
+            ),
+            Volume::Union(a, b) => {
+                let (a_min, a_max) = a.bounds();
+                let (b_min, b_max) = b.bounds();
+                (a_min.inf(&b_min), a_max.sup(&b_max))
+            }
+            Volume::Intersection(a, b) => {
+                let (a_min, a_max) = a.bounds();
+                let (b_min, b_max) = b.bounds();
+                (a_min.sup(&b_min), a_max.inf(&b_max))
+            }
+            Volume::Difference(a, _) => a.bounds(),
+            Volume::Transformed { volume, translation, angle, scale } => {
+                let (min, max) = volume.bounds();
+                let finite = min.iter().chain(max.iter()).all(|c| c.is_finite());
+                if !finite {
+                    // Rotating an unbounded box would mix infinities, so only an unrotated one keeps finite axes
+                    if *angle != 0.0 {
+                        return centred(Vector2::repeat(f64::INFINITY));
+                    }
+                    return (min * *scale + translation, max * *scale + translation);
+                }
+                let rotation = Rotation2::new(*angle);
+                let mut world_min = Vector2::repeat(f64::INFINITY);
+                let mut world_max = Vector2::repeat(f64::NEG_INFINITY);
+                for corner in 0..4 {
+                    let local = Vector2::new(
+                        if corner & 1 == 0 { min.x } else { max.x },
+                        if corner & 2 == 0 { min.y } else { max.y },
+                    );
+                    let world = rotation * (local * *scale) + translation;
+                    world_min = world_min.inf(&world);
+                    world_max = world_max.sup(&world);
+                }
+                (world_min, world_max)
+            }
+        }
+    }
+}
\ No newline at end of file
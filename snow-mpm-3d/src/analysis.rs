@@ -0,0 +1,158 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use nalgebra::{Vector2, Vector3};
+use three_d::CpuTexture;
+use three_d_asset::io::Serialize;
+use three_d_asset::TextureData;
+use crate::particle::Particle;
+use crate::terrain::Heightfield;
+
+/// Snow projected onto a horizontal grid of columns covering `[0, size.x] x [0, size.y]` in x and z.
+pub struct DepositMap {
+    pub resolution: Vector2<usize>,
+    pub size: Vector2<f32>,
+    /// Height of the highest particle above the ground in every column, x fastest.
+    pub depth: Vec<f32>,
+    pub mass: Vec<f32>,
+    /// Mass over particle volume, zero for empty columns.
+    pub density: Vec<f32>,
+}
+
+impl DepositMap {
+    /// Bins the particles into columns. The ground is the heightfield if given, otherwise `y = 0`.
+    pub fn new(particles: &[Particle], resolution: Vector2<usize>, size: Vector2<f32>, ground: Option<&Heightfield>) -> Self {
+        let cells = resolution.x * resolution.y;
+        let cell_size = size.component_div(&resolution.cast::<f32>());
+        let mut top = vec![f32::NEG_INFINITY; cells];
+        let mut mass = vec![0.0; cells];
+        let mut volume = vec![0.0; cells];
+
+        for particle in particles {
+            let i = (particle.pos.x / cell_size.x).floor();
+            let j = (particle.pos.z / cell_size.y).floor();
+            if i < 0.0 || j < 0.0 || i as usize >= resolution.x || j as usize >= resolution.y {
+                continue;
+            }
+            let index = i as usize + resolution.x * j as usize;
+            top[index] = top[index].max(particle.pos.y);
+            mass[index] += particle.mass;
+            volume[index] += particle.vol;
+        }
+
+        let mut depth = vec![0.0; cells];
+        let mut density = vec![0.0; cells];
+        for j in 0..resolution.y {
+            for i in 0..resolution.x {
+                let index = i + resolution.x * j;
+                if mass[index] == 0.0 {
+                    continue;
+                }
+                let center = Vector2::new(i as f32 + 0.5, j as f32 + 0.5).component_mul(&cell_size);
+                let ground = ground.map_or(0.0, |heightfield| heightfield.height(center.x, center.y));
+                depth[index] = (top[index] - ground).max(0.0);
+                if volume[index] > 0.0 {
+                    density[index] = mass[index] / volume[index];
+                }
+            }
+        }
+
+        DepositMap {
+            resolution,
+            size,
+            depth,
+            mass,
+            density,
+        }
+    }
+
+    /// One row per column with its centre and the three maps.
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let cell_size = self.size.component_div(&self.resolution.cast::<f32>());
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "i,j,x,z,depth,mass,density")?;
+        for j in 0..self.resolution.y {
+            for i in 0..self.resolution.x {
+                let index = i + self.resolution.x * j;
+                let x = (i as f32 + 0.5) * cell_size.x;
+                let z = (j as f32 + 0.5) * cell_size.y;
+                writeln!(file, "{},{},{},{},{},{},{}", i, j, x, z, self.depth[index], self.mass[index], self.density[index])?;
+            }
+        }
+        file.flush()
+    }
+
+    /// Writes `<prefix>-depth.png`, `<prefix>-mass.png` and `<prefix>-density.png`, each scaled to its own maximum.
+    pub fn save_heatmaps(&self, prefix: &str) -> io::Result<()> {
+        for (name, values) in [("depth", &self.depth), ("mass", &self.mass), ("density", &self.density)] {
+            save_heatmap(values, self.resolution, &format!("{}-{}.png", prefix, name))?;
+        }
+        Ok(())
+    }
+}
+
+/// Black through red and yellow to white, with image rows running along z.
+fn save_heatmap(values: &[f32], resolution: Vector2<usize>, path: &str) -> io::Result<()> {
+    let max = values.iter().cloned().fold(0.0, f32::max);
+    let pixels = values.iter().map(|&v| {
+        let t = if max > 0.0 { v / max } else { 0.0 };
+        let channel = |offset: f32| ((3.0 * t - offset).clamp(0.0, 1.0) * 255.0) as u8;
+        [channel(0.0), channel(1.0), channel(2.0), 255]
+    }).collect();
+
+    let invalid = |e: three_d_asset::Error| io::Error::other(e.to_string());
+    let texture = CpuTexture {
+        data: TextureData::RgbaU8(pixels),
+        width: resolution.x as u32,
+        height: resolution.y as u32,
+        ..Default::default()
+    };
+    three_d_asset::io::save(&texture.serialize(path).map_err(invalid)?).map_err(invalid)
+}
+
+/// Centre of mass and runout of the snow over time. The runout is how far the front has travelled from `origin`
+/// along the horizontal `direction`, taken at the 99th percentile of the particles so single stragglers don't count.
+pub struct DepositTracker {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>,
+    /// Time, centre of mass and runout of every recorded step.
+    pub records: Vec<(f32, Vector3<f32>, f32)>,
+}
+
+impl DepositTracker {
+    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Self {
+        DepositTracker {
+            origin,
+            direction: Vector3::new(direction.x, 0.0, direction.z).normalize(),
+            records: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, time: f32, particles: &[Particle]) {
+        if particles.is_empty() {
+            return;
+        }
+
+        let total_mass: f32 = particles.iter().map(|p| p.mass).sum();
+        let center_of_mass = particles.iter().map(|p| p.pos * p.mass).sum::<Vector3<f32>>() / total_mass;
+
+        let mut distances: Vec<f32> = particles.iter().map(|p| (p.pos - self.origin).dot(&self.direction)).collect();
+        distances.sort_by(|a, b| a.total_cmp(b));
+        let runout = distances[((distances.len() - 1) as f32 * 0.99) as usize].max(0.0);
+
+        self.records.push((time, center_of_mass, runout));
+    }
+
+    pub fn runout(&self) -> f32 {
+        self.records.iter().map(|record| record.2).fold(0.0, f32::max)
+    }
+
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "time,com_x,com_y,com_z,runout")?;
+        for (time, center_of_mass, runout) in &self.records {
+            writeln!(file, "{},{},{},{},{}", time, center_of_mass.x, center_of_mass.y, center_of_mass.z, runout)?;
+        }
+        file.flush()
+    }
+}
\ No newline at end of file
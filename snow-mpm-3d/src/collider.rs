@@ -0,0 +1,318 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+use nalgebra::{Isometry3, Matrix4, Translation3, UnitQuaternion, Vector2, Vector3};
+use three_d::{ColorMaterial, Context, CpuMesh, Gm, Indices, Mat4, Mesh, Positions, Srgba, vec3};
+
+pub trait Collider {
+    /// Signed distance from `position` to the collider surface, negative inside.
+    fn signed_distance(&self, position: Vector3<f32>) -> f32;
+
+    fn contact(&self) -> Contact;
+
+    /// Velocity of the collider surface at `position`.
+    fn velocity_at(&self, _position: Vector3<f32>) -> Vector3<f32> {
+        Vector3::zeros()
+    }
+
+    /// Temperature the collider holds the grid nodes touching it at, if it is a heat source.
+    fn temperature(&self) -> Option<f32> {
+        None
+    }
+
+    /// Whether a grid node at `position` touches the collider and takes its temperature.
+    fn touches(&self, position: Vector3<f32>, h: f32) -> bool {
+        self.signed_distance(position) < h
+    }
+
+    /// Outward surface normal, estimated from the gradient of the signed distance.
+    fn normal(&self, position: Vector3<f32>) -> Vector3<f32> {
+        let eps = 1e-3;
+        let dx = Vector3::new(eps, 0.0, 0.0);
+        let dy = Vector3::new(0.0, eps, 0.0);
+        let dz = Vector3::new(0.0, 0.0, eps);
+        let gradient = Vector3::new(
+            self.signed_distance(position + dx) - self.signed_distance(position - dx),
+            self.signed_distance(position + dy) - self.signed_distance(position - dy),
+            self.signed_distance(position + dz) - self.signed_distance(position - dz),
+        );
+        if gradient.norm() > 0.0 { gradient.normalize() } else { Vector3::new(0.0, 1.0, 0.0) }
+    }
+
+    /// Applies the boundary condition to a point that is inside the collider or about to enter it.
+    fn collide(&self, position: Vector3<f32>, velocity: Vector3<f32>, delta_t: f32) -> Vector3<f32> {
+        let next_pos = position + velocity * delta_t;
+        if self.signed_distance(position) > 0.0 && self.signed_distance(next_pos) > 0.0 {
+            return velocity;
+        }
+
+        let vel_collider = self.velocity_at(position);
+        vel_collider + self.contact().respond(velocity - vel_collider, self.normal(position))
+    }
+
+    /// Moves a point that ended up inside the collider to the nearest point on its surface.
+    fn project(&self, position: Vector3<f32>) -> Vector3<f32> {
+        let distance = self.signed_distance(position);
+        if distance < 0.0 {
+            position - self.normal(position) * distance
+        } else {
+            position
+        }
+    }
+
+    /// Receives the momentum the collider took out of the snow at `position` during the last grid update.
+    fn add_impulse(&mut self, _position: Vector3<f32>, _impulse: Vector3<f32>) {}
+
+    /// Moves the collider forward by one time step.
+    fn advance(&mut self, _delta_t: f32) {}
+
+    /// Pushes the collider out of the `others` it overlaps. Only dynamic colliders react.
+    fn resolve_contacts(&mut self, _others: &[&dyn Collider]) {}
+
+    fn get_materials(&self, context: &Context) -> Vec<Gm<Mesh, ColorMaterial>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoundaryCondition {
+    /// No-slip: the snow moves with the collider.
+    Sticky,
+    /// Frictionless: the normal velocity is removed, the tangential velocity is kept.
+    Slip,
+    /// The normal velocity is removed and the tangential velocity is scaled by the factor, whichever way the snow
+    /// moves. This is how the walls of the 2D domain originally behaved.
+    Damped(f32),
+    /// Coulomb friction while approaching, snow moving away is left free.
+    Separating,
+    /// Like `Separating`, but the approaching normal velocity bounces back scaled by the coefficient.
+    Restitution(f32),
+}
+
+/// How a collider treats the snow touching it.
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub boundary: BoundaryCondition,
+    /// Tangential slip below `static_friction` times the normal velocity is stopped completely.
+    pub static_friction: f32,
+    /// Faster slip is slowed by `dynamic_friction` times the normal velocity.
+    pub dynamic_friction: f32,
+}
+
+impl Contact {
+    pub fn new(boundary: BoundaryCondition, static_friction: f32, dynamic_friction: f32) -> Self {
+        Contact {
+            boundary,
+            static_friction,
+            dynamic_friction,
+        }
+    }
+
+    /// Separating contact with a single friction coefficient.
+    pub fn coulomb(mu: f32) -> Self {
+        Contact::new(BoundaryCondition::Separating, mu, mu)
+    }
+
+    /// Velocity relative to the collider after contact, `normal` pointing out of the collider.
+    pub fn respond(&self, vel_rel: Vector3<f32>, normal: Vector3<f32>) -> Vector3<f32> {
+        let v_n = vel_rel.dot(&normal);
+        let velocity_tangent = vel_rel - normal * v_n;
+        let bounce = match self.boundary {
+            BoundaryCondition::Sticky => return Vector3::zeros(),
+            BoundaryCondition::Slip => return velocity_tangent,
+            BoundaryCondition::Damped(factor) => return factor * velocity_tangent,
+            _ if v_n >= 0.0 => return vel_rel,
+            BoundaryCondition::Restitution(restitution) => -restitution * v_n * normal,
+            BoundaryCondition::Separating => Vector3::zeros(),
+        };
+
+        let mag_velocity_tangent = velocity_tangent.norm();
+        if mag_velocity_tangent <= -self.static_friction * v_n {
+            bounce
+        } else {
+            bounce + (1.0 + self.dynamic_friction * v_n / mag_velocity_tangent).max(0.0) * velocity_tangent
+        }
+    }
+}
+
+/// Advances every collider by one time step and then lets dynamic ones resolve contacts with the rest.
+pub fn advance_colliders(colliders: &mut [Box<dyn Collider>], delta_t: f32) {
+    for collider in colliders.iter_mut() {
+        collider.advance(delta_t);
+    }
+    for i in 0..colliders.len() {
+        let (before, rest) = colliders.split_at_mut(i);
+        let (collider, after) = rest.split_first_mut().unwrap();
+        let others: Vec<&dyn Collider> = before.iter().chain(after.iter()).map(|other| other.as_ref()).collect();
+        collider.resolve_contacts(&others);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Shape {
+    Sphere { radius: f32 },
+    /// Segment along the local y axis from `-half_length` to `half_length`, swept by `radius`.
+    Capsule { half_length: f32, radius: f32 },
+    /// Along the local y axis.
+    Cylinder { half_height: f32, radius: f32 },
+    Box { half_extents: Vector3<f32> },
+    /// Lies in the local xz plane.
+    Torus { major_radius: f32, minor_radius: f32 },
+    /// Everything below the local xz plane is solid.
+    HalfSpace,
+}
+
+impl Shape {
+    pub fn signed_distance(&self, p: Vector3<f32>) -> f32 {
+        match *self {
+            Shape::Sphere { radius } => p.norm() - radius,
+            Shape::Capsule { half_length, radius } => {
+                let q = Vector3::new(p.x, p.y - p.y.clamp(-half_length, half_length), p.z);
+                q.norm() - radius
+            }
+            Shape::Cylinder { half_height, radius } => {
+                let d = Vector2::new(Vector2::new(p.x, p.z).norm() - radius, p.y.abs() - half_height);
+                d.x.max(d.y).min(0.0) + d.map(|c| c.max(0.0)).norm()
+            }
+            Shape::Box { half_extents } => {
+                let q = p.abs() - half_extents;
+                q.map(|c| c.max(0.0)).norm() + q.max().min(0.0)
+            }
+            Shape::Torus { major_radius, minor_radius } => {
+                let q = Vector2::new(Vector2::new(p.x, p.z).norm() - major_radius, p.y);
+                q.norm() - minor_radius
+            }
+            Shape::HalfSpace => p.y,
+        }
+    }
+
+    /// Half extents of the local axis-aligned bounding box.
+    pub fn bounds(&self) -> Vector3<f32> {
+        match *self {
+            Shape::Sphere { radius } => Vector3::repeat(radius),
+            Shape::Capsule { half_length, radius } => Vector3::new(radius, half_length + radius, radius),
+            Shape::Cylinder { half_height, radius } => Vector3::new(radius, half_height, radius),
+            Shape::Box { half_extents } => half_extents,
+            Shape::Torus { major_radius, minor_radius } => Vector3::new(major_radius + minor_radius, minor_radius, major_radius + minor_radius),
+            Shape::HalfSpace => panic!("a half space is unbounded"),
+        }
+    }
+
+    /// Meshes in the local frame of the shape.
+    pub fn meshes(&self) -> Vec<(CpuMesh, Matrix4<f32>)> {
+        // three-d cylinders run along the x axis from 0 to 1, turn them to be centred on the y axis
+        let y_cylinder = |half_height: f32, radius: f32| {
+            Matrix4::new_rotation(Vector3::z() * FRAC_PI_2)
+                * Matrix4::new_nonuniform_scaling(&Vector3::new(2.0 * half_height, radius, radius))
+                * Matrix4::new_translation(&Vector3::new(-0.5, 0.0, 0.0))
+        };
+
+        match *self {
+            Shape::Sphere { radius } => vec![(CpuMesh::sphere(16), Matrix4::new_scaling(radius))],
+            Shape::Capsule { half_length, radius } => vec![
+                (CpuMesh::cylinder(16), y_cylinder(half_length, radius)),
+                (CpuMesh::sphere(16), Matrix4::new_translation(&Vector3::new(0.0, half_length, 0.0)) * Matrix4::new_scaling(radius)),
+                (CpuMesh::sphere(16), Matrix4::new_translation(&Vector3::new(0.0, -half_length, 0.0)) * Matrix4::new_scaling(radius)),
+            ],
+            Shape::Cylinder { half_height, radius } => vec![(CpuMesh::cylinder(16), y_cylinder(half_height, radius))],
+            Shape::Box { half_extents } => vec![(CpuMesh::cube(), Matrix4::new_nonuniform_scaling(&half_extents))],
+            Shape::Torus { major_radius, minor_radius } => vec![(torus_mesh(major_radius, minor_radius, 32, 12), Matrix4::identity())],
+            Shape::HalfSpace => vec![(CpuMesh::square(), Matrix4::new_rotation(Vector3::x() * -FRAC_PI_2) * Matrix4::new_scaling(10.0))],
+        }
+    }
+}
+
+fn torus_mesh(major_radius: f32, minor_radius: f32, major_segments: u32, minor_segments: u32) -> CpuMesh {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for i in 0..major_segments {
+        let theta = 2.0 * PI * i as f32 / major_segments as f32;
+        for j in 0..minor_segments {
+            let phi = 2.0 * PI * j as f32 / minor_segments as f32;
+            let r = major_radius + minor_radius * phi.cos();
+            positions.push(vec3(r * theta.cos(), minor_radius * phi.sin(), r * theta.sin()));
+
+            let next_i = (i + 1) % major_segments;
+            let next_j = (j + 1) % minor_segments;
+            let a = i * minor_segments + j;
+            let b = next_i * minor_segments + j;
+            let c = next_i * minor_segments + next_j;
+            let d = i * minor_segments + next_j;
+            indices.extend_from_slice(&[a, b, c, a, c, d]);
+        }
+    }
+
+    let mut mesh = CpuMesh {
+        positions: Positions::F32(positions),
+        indices: Indices::U32(indices),
+        ..Default::default()
+    };
+    mesh.compute_normals();
+    mesh
+}
+
+pub fn to_mat4(matrix: Matrix4<f32>) -> Mat4 {
+    let columns: [[f32; 4]; 4] = matrix.into();
+    Mat4::from(columns)
+}
+
+/// A solid shape described by a signed distance function, placed in grid coordinates by a rigid transform.
+pub struct SdfCollider {
+    shape: Shape,
+    translation: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+    contact: Contact,
+    color: Srgba,
+    temperature: Option<f32>,
+}
+
+impl SdfCollider {
+    pub fn new(shape: Shape, translation: Vector3<f32>, rotation: UnitQuaternion<f32>, mu: f32, color: Srgba) -> Self {
+        SdfCollider {
+            shape,
+            translation,
+            rotation,
+            contact: Contact::coulomb(mu),
+            color,
+            temperature: None,
+        }
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Replaces the default separating Coulomb contact.
+    pub fn with_contact(mut self, contact: Contact) -> Self {
+        self.contact = contact;
+        self
+    }
+
+    fn isometry(&self) -> Isometry3<f32> {
+        Isometry3::from_parts(Translation3::from(self.translation), self.rotation)
+    }
+}
+
+impl Collider for SdfCollider {
+    fn signed_distance(&self, position: Vector3<f32>) -> f32 {
+        let local = self.rotation.inverse() * (position - self.translation);
+        self.shape.signed_distance(local)
+    }
+
+    fn contact(&self) -> Contact {
+        self.contact
+    }
+
+    fn temperature(&self) -> Option<f32> {
+        self.temperature
+    }
+
+    fn get_materials(&self, context: &Context) -> Vec<Gm<Mesh, ColorMaterial>> {
+        let model = self.isometry().to_homogeneous();
+        self.shape.meshes().into_iter().map(|(cpu_mesh, local)| {
+            let mut gm = Gm::new(Mesh::new(context, &cpu_mesh), ColorMaterial {
+                color: self.color,
+                ..Default::default()
+            });
+            gm.set_transformation(to_mat4(model * local));
+            gm
+        }).collect()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,93 @@
+use nalgebra::Vector3;
+use crate::collider::Contact;
+use crate::particle::Particle;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Boundary {
+    /// Particles that cross the face are put back on it, the grid nodes next to it are left alone.
+    Clamp,
+    /// Solid wall: grid nodes next to it get the contact response and particles are kept inside.
+    Wall(Contact),
+    /// Particles that leave through the face are deleted.
+    Open,
+    /// Particles leaving through the face come back in through the opposite one, and grid transfers wrap around.
+    /// Must be set on both faces of an axis.
+    Periodic,
+}
+
+/// Behaviour of the six faces of the simulation box, in the order -x, +x, -y, +y, -z, +z.
+#[derive(Debug, Clone, Copy)]
+pub struct Domain {
+    pub faces: [Boundary; 6],
+}
+
+impl Domain {
+    pub fn new(faces: [Boundary; 6]) -> Self {
+        for axis in 0..3 {
+            let lower = matches!(faces[2 * axis], Boundary::Periodic);
+            let upper = matches!(faces[2 * axis + 1], Boundary::Periodic);
+            if lower != upper {
+                panic!("periodic boundaries must be set on both faces of an axis");
+            }
+        }
+        Domain {
+            faces,
+        }
+    }
+
+    /// Every face clamps the particles, the default.
+    pub fn clamped() -> Self {
+        Domain::new([Boundary::Clamp; 6])
+    }
+
+    pub fn walls(contact: Contact) -> Self {
+        Domain::new([Boundary::Wall(contact); 6])
+    }
+
+    pub fn periodic(&self) -> Vector3<bool> {
+        Vector3::from_fn(|axis, _| matches!(self.faces[2 * axis], Boundary::Periodic))
+    }
+
+    /// Applies the wall faces to a grid node at `position` that is next to them or about to cross them.
+    pub fn collide(&self, position: Vector3<f32>, velocity: Vector3<f32>, dims: Vector3<f32>, h: f32, delta_t: f32) -> Vector3<f32> {
+        let next_pos = position + velocity * delta_t;
+        let mut velocity = velocity;
+        for axis in 0..3 {
+            let mut normal = Vector3::zeros();
+            normal[axis] = 1.0;
+            if let Boundary::Wall(contact) = self.faces[2 * axis] {
+                if next_pos[axis] < h {
+                    velocity = contact.respond(velocity, normal);
+                }
+            }
+            if let Boundary::Wall(contact) = self.faces[2 * axis + 1] {
+                if next_pos[axis] > dims[axis] - 2.0 * h {
+                    velocity = contact.respond(velocity, -normal);
+                }
+            }
+        }
+        velocity
+    }
+
+    /// Keeps the particle inside the box: clamps it against walls and wraps it across periodic faces.
+    /// Returns false if it left through an open face and should be deleted.
+    pub fn confine(&self, particle: &mut Particle, dims: Vector3<f32>) -> bool {
+        for axis in 0..3 {
+            let upper = dims[axis] - 1e-5;
+            if particle.pos[axis] < 0.0 {
+                match self.faces[2 * axis] {
+                    Boundary::Clamp | Boundary::Wall(_) => particle.pos[axis] = 0.0,
+                    Boundary::Open => return false,
+                    Boundary::Periodic => particle.pos[axis] = particle.pos[axis].rem_euclid(dims[axis]).min(upper),
+                }
+            } else if particle.pos[axis] > upper {
+                match self.faces[2 * axis + 1] {
+                    Boundary::Clamp | Boundary::Wall(_) => particle.pos[axis] = upper,
+                    Boundary::Open => return false,
+                    Boundary::Periodic => particle.pos[axis] = particle.pos[axis].rem_euclid(dims[axis]).min(upper),
+                }
+            }
+        }
+        true
+    }
+}
\ No newline at end of file
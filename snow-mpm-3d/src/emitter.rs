@@ -0,0 +1,136 @@
+use nalgebra::{UnitQuaternion, Vector3};
+use rand::Rng;
+use crate::collider::Shape;
+use crate::noise::Heterogeneity;
+
+/// A shape placed in grid coordinates, used for the volumes emitters fill and sinks empty.
+pub struct Region {
+    pub shape: Shape,
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+}
+
+impl Region {
+    /// `None` for a half space, which has no bounds to sample or clip against.
+    pub fn new(shape: Shape, translation: Vector3<f32>, rotation: UnitQuaternion<f32>) -> Option<Self> {
+        if let Shape::HalfSpace = shape {
+            return None;
+        }
+        Some(Region {
+            shape,
+            translation,
+            rotation,
+        })
+    }
+
+    pub fn contains(&self, position: Vector3<f32>) -> bool {
+        self.shape.signed_distance(self.rotation.inverse() * (position - self.translation)) <= 0.0
+    }
+
+    /// Axis-aligned bounding box in grid coordinates.
+    pub fn aabb(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let bounds = self.shape.bounds();
+        let mut min = Vector3::repeat(f32::INFINITY);
+        let mut max = Vector3::repeat(f32::NEG_INFINITY);
+        for corner in 0..8 {
+            let local = Vector3::new(
+                if corner & 1 == 0 { -bounds.x } else { bounds.x },
+                if corner & 2 == 0 { -bounds.y } else { bounds.y },
+                if corner & 4 == 0 { -bounds.z } else { bounds.z },
+            );
+            let world = self.rotation * local + self.translation;
+            min = min.inf(&world);
+            max = max.sup(&world);
+        }
+        (min, max)
+    }
+
+    /// Uniformly distributed point inside the region, by rejection sampling its bounding box.
+    pub fn sample(&self, rng: &mut impl Rng) -> Vector3<f32> {
+        let bounds = self.shape.bounds();
+        loop {
+            let local = Vector3::new(
+                rng.gen_range(-bounds.x..=bounds.x),
+                rng.gen_range(-bounds.y..=bounds.y),
+                rng.gen_range(-bounds.z..=bounds.z),
+            );
+            if self.shape.signed_distance(local) <= 0.0 {
+                return self.rotation * local + self.translation;
+            }
+        }
+    }
+}
+
+/// Spawns particles inside a region at a steady rate, e.g. snowfall from a thin box below the top of the domain
+/// or a snow cannon nozzle. All particles of an emitter form one body.
+pub struct Emitter {
+    pub region: Region,
+    /// Particles per second.
+    pub rate: f32,
+    pub vel: Vector3<f32>,
+    /// Mass of every emitted particle, before heterogeneity is applied.
+    pub particle_mass: f32,
+    pub temperature: f32,
+    pub heterogeneity: Option<Heterogeneity>,
+    /// Simulation time window during which the emitter runs, open-ended if `end` is `None`.
+    pub start: f32,
+    pub end: Option<f32>,
+    pub(crate) body: usize,
+    pending: f32,
+}
+
+impl Emitter {
+    /// Particles get the mass of `particle_volume` worth of snow at `density`.
+    pub fn new(region: Region, rate: f32, vel: Vector3<f32>, density: f32, particle_volume: f32, temperature: f32) -> Self {
+        Emitter {
+            region,
+            rate,
+            vel,
+            particle_mass: density * particle_volume,
+            temperature,
+            heterogeneity: None,
+            start: 0.0,
+            end: None,
+            body: 0,
+            pending: 0.0,
+        }
+    }
+
+    pub fn with_heterogeneity(mut self, heterogeneity: Heterogeneity) -> Self {
+        self.heterogeneity = Some(heterogeneity);
+        self
+    }
+
+    pub fn with_window(mut self, start: f32, end: Option<f32>) -> Self {
+        self.start = start;
+        self.end = end;
+        self
+    }
+
+    /// Number of particles to spawn in the step starting at `time`. Fractions are carried over to later steps.
+    pub fn count(&mut self, time: f32, delta_t: f32) -> usize {
+        if time < self.start || self.end.is_some_and(|end| time >= end) {
+            return 0;
+        }
+        self.pending += self.rate * delta_t;
+        let count = self.pending.floor();
+        self.pending -= count;
+        count as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_must_be_bounded() {
+        assert!(Region::new(Shape::HalfSpace, Vector3::zeros(), UnitQuaternion::identity()).is_none());
+
+        let region = Region::new(Shape::Sphere { radius: 1.0 }, Vector3::new(2.0, 0.0, 0.0), UnitQuaternion::identity()).unwrap();
+        let (min, max) = region.aabb();
+        assert_eq!(min, Vector3::new(1.0, -1.0, -1.0));
+        assert_eq!(max, Vector3::new(3.0, 1.0, 1.0));
+        assert!(region.contains(region.sample(&mut rand::thread_rng())));
+    }
+}
\ No newline at end of file
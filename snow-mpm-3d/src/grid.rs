@@ -1,10 +1,45 @@
 use std::collections::HashSet;
+use std::f32::consts::PI;
 use nalgebra::{clamp, Matrix3, Vector3, SVD};
 use rand::Rng;
+use crate::collider::Collider;
+use crate::domain::Domain;
+use crate::emitter::{Emitter, Region};
+use crate::sampling;
 use crate::helpers::Helpers;
+use crate::mesh::TriangleMesh;
+use crate::noise::Heterogeneity;
 use crate::params::Params;
 use crate::particle::Particle;
-use crate::plane::{Cube, Plane};
+use crate::probe::Probe;
+use crate::sensor::{self, ForceSensor};
+use crate::plasticity::{self, PlasticityModel, Sintering};
+use crate::thermal::{Phase, Thermal};
+use crate::terrain::Heightfield;
+use crate::volume::Volume;
+use crate::vox::VoxModel;
+
+/// Velocity field of a single body at a grid node, used in multi-field mode.
+#[derive(Clone)]
+struct FieldNode {
+    mass: f32,
+    mass_grad: Vector3<f32>,
+    vel: Vector3<f32>,
+    next_vel: Vector3<f32>,
+    force: Vector3<f32>,
+}
+
+impl FieldNode {
+    fn new() -> Self {
+        FieldNode {
+            mass: 0.0,
+            mass_grad: Vector3::new(0.0, 0.0, 0.0),
+            vel: Vector3::new(0.0, 0.0, 0.0),
+            next_vel: Vector3::new(0.0, 0.0, 0.0),
+            force: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
 
 #[derive(Clone)]
 struct GridNode {
@@ -13,6 +48,11 @@ struct GridNode {
     vel: Vector3<f32>,
     next_vel: Vector3<f32>,
     force: Vector3<f32>,
+    heat_capacity: f32,
+    temperature: f32,
+    next_temperature: f32,
+    heat: f32,
+    fields: Vec<FieldNode>,
 }
 
 impl GridNode {
@@ -23,15 +63,44 @@ impl GridNode {
             vel: Vector3::new(0.0, 0.0, 0.0),
             next_vel: Vector3::new(0.0, 0.0, 0.0),
             force: Vector3::new(0.0, 0.0, 0.0),
+            heat_capacity: 0.0,
+            temperature: 0.0,
+            next_temperature: 0.0,
+            heat: 0.0,
+            fields: Vec::new(),
         }
     }
 
+    fn velocity(&self, body: usize) -> Vector3<f32> {
+        self.fields.get(body).map_or(self.vel, |field| field.vel)
+    }
+
+    fn next_velocity(&self, body: usize) -> Vector3<f32> {
+        self.fields.get(body).map_or(self.next_vel, |field| field.next_vel)
+    }
+
     fn reset(&mut self) {
         self.mass = 0.0;
         self.vel = Vector3::new(0.0, 0.0, 0.0);
         self.next_vel = Vector3::new(0.0, 0.0, 0.0);
         self.force = Vector3::new(0.0, 0.0, 0.0);
+        self.heat_capacity = 0.0;
+        self.temperature = 0.0;
+        self.next_temperature = 0.0;
+        self.heat = 0.0;
+        self.fields.clear();
+    }
+}
+
+fn heat_source_temperature(position: Vector3<f32>, h: f32, colliders: &[Box<dyn Collider>]) -> Option<f32> {
+    for co in colliders {
+        if let Some(temperature) = co.temperature() {
+            if co.touches(position, h) {
+                return Some(temperature);
+            }
+        }
     }
+    None
 }
 
 pub struct Grid {
@@ -44,6 +113,13 @@ pub struct Grid {
     pub all_particles: Vec<Particle>,
     nodes_in_use: HashSet<Vector3<usize>>,
     first_step: bool,
+    num_bodies: usize,
+    pub domain: Domain,
+    emitters: Vec<Emitter>,
+    sinks: Vec<Region>,
+    pub sensors: Vec<ForceSensor>,
+    pub probes: Vec<Probe>,
+    time: f32,
 }
 
 impl Grid {
@@ -65,6 +141,13 @@ impl Grid {
             all_particles: Vec::new(),
             nodes_in_use: HashSet::new(),
             first_step: true,
+            num_bodies: 0,
+            domain: Domain::clamped(),
+            emitters: Vec::new(),
+            sinks: Vec::new(),
+            sensors: Vec::new(),
+            probes: Vec::new(),
+            time: 0.0,
         }
     }
 
@@ -75,26 +158,32 @@ impl Grid {
             node.vel = Vector3::new(0.0, 0.0, 0.0);
             node.next_vel = Vector3::new(0.0, 0.0, 0.0);
             node.force = Vector3::new(0.0, 0.0, 0.0);
+            node.heat_capacity = 0.0;
+            node.temperature = 0.0;
+            node.next_temperature = 0.0;
+            node.heat = 0.0;
+            node.fields.clear();
         }
 
-        for particle in self.all_particles.iter_mut() {
-            particle.pos.x = clamp(particle.pos.x, 0.0, self.dim_x - 1e-5);
-            particle.pos.y = clamp(particle.pos.y, 0.0, self.dim_y - 1e-5);
-            particle.pos.z = clamp(particle.pos.z, 0.0, self.dim_z - 1e-5);
+        let dims = Vector3::new(self.dim_x, self.dim_y, self.dim_z);
+        self.all_particles.retain_mut(|particle| self.domain.confine(particle, dims));
 
-            particle.compute_neighborhood_bounds();
+        let periodic = self.domain.periodic();
+        for particle in self.all_particles.iter_mut() {
+            particle.compute_neighborhood_bounds(periodic);
             particle.compute_b_spline_d();
             for dest_i in particle.i1..particle.i2 {
                 for dest_j in particle.j1..particle.j2 {
                     for dest_k in particle.k1..particle.k2 {
-                        let node = &mut self.nodes[dest_i][dest_j][dest_k];
-                        let node_init = &mut self.node_init[dest_i][dest_j][dest_k];
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        let node = &mut self.nodes[index.x][index.y][index.z];
+                        let node_init = &mut self.node_init[index.x][index.y][index.z];
 
                         if !*node_init {
                             node.reset();
-                            node.index = Vector3::new(dest_i as f32, dest_j as f32, dest_k as f32);
+                            node.index = index.cast::<f32>();
                         }
-                        self.nodes_in_use.insert(Vector3::new(dest_i, dest_j, dest_k));
+                        self.nodes_in_use.insert(index);
                     }
                 }
             }
@@ -107,7 +196,8 @@ impl Grid {
                 for dest_j in particle.j1..particle.j2 {
                     for dest_k in particle.k1..particle.k2 {
                         let weight = particle.b_spline_at(dest_i, dest_j, dest_k);
-                        let node = &mut self.nodes[dest_i][dest_j][dest_k];
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        let node = &mut self.nodes[index.x][index.y][index.z];
                         node.mass += weight * particle.mass;
                         node.vel += weight * particle.mass * particle.vel;
                     }
@@ -123,6 +213,107 @@ impl Grid {
         }
     }
 
+    fn particle_to_fields(&mut self) {
+        for node_index in &self.nodes_in_use {
+            let node = &mut self.nodes[node_index.x][node_index.y][node_index.z];
+            // The fields were cleared with the node, so this reuses their allocation from the previous step
+            node.fields.resize(self.num_bodies, FieldNode::new());
+        }
+
+        for particle in &self.all_particles {
+            for dest_i in particle.i1..particle.i2 {
+                for dest_j in particle.j1..particle.j2 {
+                    for dest_k in particle.k1..particle.k2 {
+                        let weight = particle.b_spline_at(dest_i, dest_j, dest_k);
+                        let weight_grad = particle.b_spline_grad_at(dest_i, dest_j, dest_k);
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        let field = &mut self.nodes[index.x][index.y][index.z].fields[particle.body];
+                        field.mass += weight * particle.mass;
+                        field.mass_grad += weight_grad * particle.mass;
+                        field.vel += weight * particle.mass * particle.vel;
+                    }
+                }
+            }
+        }
+
+        for node_index in &self.nodes_in_use {
+            let node = &mut self.nodes[node_index.x][node_index.y][node_index.z];
+            for field in node.fields.iter_mut() {
+                if field.mass > 0.0 {
+                    field.vel /= field.mass;
+                }
+            }
+        }
+    }
+
+    fn particle_to_grid_heat(&mut self, thermal: &Thermal) {
+        for particle in &self.all_particles {
+            let heat_capacity = particle.mass * thermal.heat_capacity(particle.phase);
+            for dest_i in particle.i1..particle.i2 {
+                for dest_j in particle.j1..particle.j2 {
+                    for dest_k in particle.k1..particle.k2 {
+                        let weight = particle.b_spline_at(dest_i, dest_j, dest_k);
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        let node = &mut self.nodes[index.x][index.y][index.z];
+                        node.heat_capacity += weight * heat_capacity;
+                        node.temperature += weight * heat_capacity * particle.temperature;
+                    }
+                }
+            }
+        }
+
+        for node_index in &self.nodes_in_use {
+            let node = &mut self.nodes[node_index.x][node_index.y][node_index.z];
+            if node.heat_capacity > 0.0 {
+                node.temperature /= node.heat_capacity;
+            }
+        }
+    }
+
+    fn compute_heat_diffusion(&mut self, delta_t: f32, thermal: &Thermal, colliders: &[Box<dyn Collider>]) {
+        for node_index in &self.nodes_in_use {
+            let node = &mut self.nodes[node_index.x][node_index.y][node_index.z];
+            if let Some(temperature) = heat_source_temperature(node.index * self.h, self.h, colliders) {
+                node.temperature = temperature;
+            }
+        }
+
+        for particle in &self.all_particles {
+            let mut grad_temperature = Vector3::zeros();
+            for dest_i in particle.i1..particle.i2 {
+                for dest_j in particle.j1..particle.j2 {
+                    for dest_k in particle.k1..particle.k2 {
+                        let weight_grad = particle.b_spline_grad_at(dest_i, dest_j, dest_k);
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        grad_temperature += self.nodes[index.x][index.y][index.z].temperature * weight_grad;
+                    }
+                }
+            }
+
+            let flux = particle.vol * thermal.conductivity(particle.phase) * grad_temperature;
+            for dest_i in particle.i1..particle.i2 {
+                for dest_j in particle.j1..particle.j2 {
+                    for dest_k in particle.k1..particle.k2 {
+                        let weight_grad = particle.b_spline_grad_at(dest_i, dest_j, dest_k);
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        self.nodes[index.x][index.y][index.z].heat -= flux.dot(&weight_grad);
+                    }
+                }
+            }
+        }
+
+        for node_index in &self.nodes_in_use {
+            let node = &mut self.nodes[node_index.x][node_index.y][node_index.z];
+            node.next_temperature = node.temperature;
+            if node.heat_capacity > 0.0 {
+                node.next_temperature += delta_t * node.heat / node.heat_capacity;
+            }
+            if let Some(temperature) = heat_source_temperature(node.index * self.h, self.h, colliders) {
+                node.next_temperature = temperature;
+            }
+        }
+    }
+
     fn compute_particle_volumes(&mut self) {
         let h3 = self.h.powi(3);
 
@@ -133,7 +324,8 @@ impl Grid {
                 for dest_j in particle.j1..particle.j2 {
                     for dest_k in particle.k1..particle.k2 {
                         let weight = particle.b_spline_at(dest_i, dest_j, dest_k);
-                        density += weight * self.nodes[dest_i][dest_j][dest_k].mass;
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        density += weight * self.nodes[index.x][index.y][index.z].mass;
                     }
                 }
             }
@@ -151,7 +343,8 @@ impl Grid {
                 for dest_j in particle.j1..particle.j2 {
                     for dest_k in particle.k1..particle.k2 {
                         let weight_grad = particle.b_spline_grad_at(dest_i, dest_j, dest_k);
-                        let velocity = self.nodes[dest_i][dest_j][dest_k].vel;
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        let velocity = self.nodes[index.x][index.y][index.z].velocity(particle.body);
                         sum += delta_t * Helpers::outer_product(velocity, weight_grad);
                     }
                 }
@@ -162,10 +355,18 @@ impl Grid {
         }
     }
 
-    fn compute_grid_forces(&mut self, mu_0: f32, lambda_0: f32, xi: f32) {
-        for particle in &self.all_particles {
+    fn compute_grid_forces(&mut self, params: &Params) {
+        let kappa = params.bulk_modulus();
+        for particle in &mut self.all_particles {
             let volume = particle.vol;
-            let sigma_p: Matrix3<f32> = Helpers::psi_derivative(mu_0, lambda_0, xi, particle) * particle.def_e_d.transpose();
+            let stiffness = particle.stiffness * params.sintering.map_or(1.0, |s| s.stiffness_factor(particle.sintering));
+            // Cam-Clay bounds the stress of its own energy, and hardens through its yield surface instead of the Lame parameters
+            let psi_derivative = match params.plasticity {
+                PlasticityModel::SingularValueClamp => Helpers::psi_derivative(params.mu_0 * stiffness, params.lambda_0 * stiffness, params.hardening_coefficient, &params.hardening, particle),
+                PlasticityModel::CamClay(_) => Helpers::cam_clay_psi_derivative(params.mu_0 * stiffness, kappa * stiffness, particle),
+            };
+            let sigma_p: Matrix3<f32> = psi_derivative * particle.def_e_d.transpose();
+            particle.stress = sigma_p / particle.f_ep_d.determinant();
 
             let neg_force_unweighted = volume * sigma_p;
 
@@ -173,14 +374,20 @@ impl Grid {
                 for dest_j in particle.j1..particle.j2 {
                     for dest_k in particle.k1..particle.k2 {
                         let weight_grad = particle.b_spline_grad_at(dest_i, dest_j, dest_k);
-                        self.nodes[dest_i][dest_j][dest_k].force -= neg_force_unweighted * weight_grad;
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        let node = &mut self.nodes[index.x][index.y][index.z];
+                        node.force -= neg_force_unweighted * weight_grad;
+                        if let Some(field) = node.fields.get_mut(particle.body) {
+                            field.force -= neg_force_unweighted * weight_grad;
+                        }
                     }
                 }
             }
         }
     }
 
-    fn compute_grid_velocities(&mut self, delta_t: f32, collision_objects: &Vec<Plane>, collision_cubes: &Vec<Cube>) {
+    fn compute_grid_velocities(&mut self, delta_t: f32, colliders: &mut [Box<dyn Collider>]) {
+        let dims = Vector3::new(self.dim_x, self.dim_y, self.dim_z);
         for node_index in &self.nodes_in_use {
             let node = &mut self.nodes[node_index.x][node_index.y][node_index.z];
             node.next_vel = node.vel;
@@ -189,27 +396,92 @@ impl Grid {
                 node.next_vel += node.force * delta_t / node.mass;
             }
 
+            // The momentum a collider takes out of the snow is handed to it, which moves dynamic rigid bodies.
+            // With separate body fields the fields carry the momentum instead of the combined node.
             let position = node.index * self.h;
-            for co in collision_objects {
-                node.next_vel = co.collide(position, node.next_vel, delta_t);
+            for (i, co) in colliders.iter_mut().enumerate() {
+                let next_vel = co.collide(position, node.next_vel, delta_t);
+                if node.fields.is_empty() {
+                    co.add_impulse(position, node.mass * (node.next_vel - next_vel));
+                    sensor::record_impulse(&mut self.sensors, i, position, node.mass * (node.next_vel - next_vel));
+                }
+                node.next_vel = next_vel;
+            }
+            node.next_vel = self.domain.collide(position, node.next_vel, dims, self.h, delta_t);
+
+            for field in node.fields.iter_mut() {
+                field.next_vel = field.vel;
+                if field.mass > 0.0 {
+                    field.next_vel += field.force * delta_t / field.mass;
+                }
+                for (i, co) in colliders.iter_mut().enumerate() {
+                    let next_vel = co.collide(position, field.next_vel, delta_t);
+                    co.add_impulse(position, field.mass * (field.next_vel - next_vel));
+                    sensor::record_impulse(&mut self.sensors, i, position, field.mass * (field.next_vel - next_vel));
+                    field.next_vel = next_vel;
+                }
+                field.next_vel = self.domain.collide(position, field.next_vel, dims, self.h, delta_t);
+            }
+        }
+    }
+
+    /// Resolves contact between bodies sharing a node: a body approaching the common centre of mass velocity along
+    /// its surface normal loses that normal component, and its tangential slip is reduced by Coulomb friction.
+    /// The normal of a body points away from its own mass and towards the mass of the others, both taken from the
+    /// summed mass gradient of the node, so two bodies in contact push along exactly opposite normals.
+    fn resolve_field_contact(&mut self, friction: f32) {
+        for node_index in &self.nodes_in_use {
+            let node = &mut self.nodes[node_index.x][node_index.y][node_index.z];
+            if node.fields.iter().filter(|field| field.mass > 0.0).count() < 2 {
+                continue;
             }
-            for cube in collision_cubes {
-                for co in cube.sides.iter() {
-                    node.next_vel = co.collide(position, node.next_vel, delta_t);
+
+            let mass: f32 = node.fields.iter().map(|field| field.mass).sum();
+            let momentum: Vector3<f32> = node.fields.iter().map(|field| field.mass * field.next_vel).sum();
+            let vel_cm = momentum / mass;
+            let mass_grad: Vector3<f32> = node.fields.iter().map(|field| field.mass_grad).sum();
+
+            for field in node.fields.iter_mut() {
+                // Gradient of this body's mass minus that of all the others
+                let separation = 2.0 * field.mass_grad - mass_grad;
+                if field.mass <= 0.0 || separation.norm() < 1e-6 {
+                    continue;
                 }
+                let normal = -separation.normalize();
+                let vel_rel = field.next_vel - vel_cm;
+                let v_n = vel_rel.dot(&normal);
+                if v_n <= 0.0 {
+                    continue;
+                }
+
+                let vel_tangent = vel_rel - normal * v_n;
+                let mag_vel_tangent = vel_tangent.norm();
+                field.next_vel = if mag_vel_tangent <= friction * v_n {
+                    vel_cm
+                } else {
+                    field.next_vel - normal * v_n - friction * v_n * vel_tangent / mag_vel_tangent
+                };
             }
         }
     }
 
-    fn update_deformation_gradients(&mut self, theta_c: f32, theta_s: f32, delta_t: f32) {
+    fn update_deformation_gradients(&mut self, params: &Params, delta_t: f32) {
+        let kappa = params.bulk_modulus();
+
         for particle in &mut self.all_particles {
+            let stiffness = particle.stiffness * params.sintering.map_or(1.0, |s| s.stiffness_factor(particle.sintering));
+            let strength = particle.strength * params.sintering.map_or(1.0, |s| s.strength_factor(particle.sintering));
+            let theta_c = params.critical_compression * strength;
+            let theta_s = params.critical_stretch * strength;
+
             let mut grad_vp = Matrix3::zeros();
 
             for dest_i in particle.i1..particle.i2 {
                 for dest_j in particle.j1..particle.j2 {
                     for dest_k in particle.k1..particle.k2 {
                         let weight_grad = particle.b_spline_grad_at(dest_i, dest_j, dest_k);
-                        let velocity = self.nodes[dest_i][dest_j][dest_k].next_vel;
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        let velocity = self.nodes[index.x][index.y][index.z].next_velocity(particle.body);
                         grad_vp += Helpers::outer_product(velocity, weight_grad);
                     }
                 }
@@ -219,15 +491,38 @@ impl Grid {
             let dgrad_e_next = (identity + delta_t * grad_vp) * particle.def_e_d;
             let f_next = dgrad_e_next * particle.def_p_d;
 
+            if particle.phase == Phase::Water {
+                // Fluids only keep the volumetric part of the elastic deformation
+                particle.def_e_d = Matrix3::from_diagonal_element(dgrad_e_next.determinant().cbrt());
+                particle.def_p_d = particle.def_e_d.try_inverse().unwrap() * f_next;
+                continue;
+            }
+
+            if let PlasticityModel::CamClay(cam_clay) = params.plasticity {
+                let log_j_p = particle.log_j_p;
+                let projected = cam_clay.project(dgrad_e_next, &mut particle.log_j_p, params.mu_0 * stiffness, kappa * stiffness);
+                particle.def_e_d = dgrad_e_next.zip_map(&projected, |trial, projected| plasticity::relax(trial, projected, delta_t, params.relaxation_time));
+                particle.log_j_p = plasticity::relax(log_j_p, particle.log_j_p, delta_t, params.relaxation_time);
+                if params.sintering.is_some_and(|s| s.bonds_break(&dgrad_e_next, &projected)) {
+                    particle.sintering = 0.0;
+                }
+                particle.def_p_d = particle.def_e_d.try_inverse().unwrap() * f_next;
+                continue;
+            }
+
             let svd_result = SVD::new(dgrad_e_next, true, true);
             let u = svd_result.u.unwrap();
             let v_t = svd_result.v_t.unwrap();
             let s_hat_vec = svd_result.singular_values;
 
-            let s_vec_x = clamp(s_hat_vec.x, 1.0 - theta_c, 1.0 + theta_s);
-            let s_vec_y = clamp(s_hat_vec.y, 1.0 - theta_c, 1.0 + theta_s);
-            let s_vec_z = clamp(s_hat_vec.z, 1.0 - theta_c, 1.0 + theta_s);
+            let s_vec_x = plasticity::relax(s_hat_vec.x, clamp(s_hat_vec.x, 1.0 - theta_c, 1.0 + theta_s), delta_t, params.relaxation_time);
+            let s_vec_y = plasticity::relax(s_hat_vec.y, clamp(s_hat_vec.y, 1.0 - theta_c, 1.0 + theta_s), delta_t, params.relaxation_time);
+            let s_vec_z = plasticity::relax(s_hat_vec.z, clamp(s_hat_vec.z, 1.0 - theta_c, 1.0 + theta_s), delta_t, params.relaxation_time);
             let s_vec = Vector3::new(s_vec_x, s_vec_y, s_vec_z);
+            if params.sintering.is_some_and(|s| s.bonds_break(&Matrix3::from_diagonal(&s_hat_vec), &Matrix3::from_diagonal(&s_vec))) {
+                // Yielding breaks the bonds formed by sintering
+                particle.sintering = 0.0;
+            }
             let s = Matrix3::new(
                 s_vec.x, 0.0, 0.0,
                 0.0, s_vec.y, 0.0,
@@ -244,6 +539,17 @@ impl Grid {
         }
     }
 
+    fn update_sintering(&mut self, sintering: &Sintering, delta_t: f32) {
+        for particle in &mut self.all_particles {
+            if particle.phase == Phase::Water {
+                particle.sintering = 0.0;
+                continue;
+            }
+            let pressure = -particle.stress.trace() / 3.0;
+            particle.sintering = sintering.update(particle.sintering, pressure, delta_t);
+        }
+    }
+
     fn update_particle_velocities(&mut self, alpha: f32) {
         for particle in &mut self.all_particles {
             let mut v_pic = Vector3::new(0.0, 0.0, 0.0);
@@ -252,10 +558,11 @@ impl Grid {
             for dest_i in particle.i1..particle.i2 {
                 for dest_j in particle.j1..particle.j2 {
                     for dest_k in particle.k1..particle.k2 {
-                        let dest = &self.nodes[dest_i][dest_j][dest_k];
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        let dest = &self.nodes[index.x][index.y][index.z];
                         let weight = particle.b_spline_at(dest_i, dest_j, dest_k);
-                        v_pic += dest.next_vel * weight;
-                        v_flip += (dest.next_vel - dest.vel) * weight;
+                        v_pic += dest.next_velocity(particle.body) * weight;
+                        v_flip += (dest.next_velocity(particle.body) - dest.velocity(particle.body)) * weight;
                     }
                 }
             }
@@ -264,16 +571,33 @@ impl Grid {
         }
     }
 
-    fn compute_particle_collisions(&mut self, delta_t: f32, collision_objects: &Vec<Plane>, collision_cubes: &Vec<Cube>) {
+    fn update_particle_temperatures(&mut self, alpha: f32, thermal: &Thermal) {
         for particle in &mut self.all_particles {
-            for co in collision_objects {
-                particle.vel = co.collide(particle.pos, particle.vel, delta_t);
-            }
-            for cube in collision_cubes {
-                for co in cube.sides.iter() {
-                    particle.vel = co.collide(particle.pos, particle.vel, delta_t);
+            let mut t_pic = 0.0;
+            let mut t_flip = particle.temperature;
+
+            for dest_i in particle.i1..particle.i2 {
+                for dest_j in particle.j1..particle.j2 {
+                    for dest_k in particle.k1..particle.k2 {
+                        let index = particle.node_index(dest_i, dest_j, dest_k);
+                        let dest = &self.nodes[index.x][index.y][index.z];
+                        let weight = particle.b_spline_at(dest_i, dest_j, dest_k);
+                        t_pic += dest.next_temperature * weight;
+                        t_flip += (dest.next_temperature - dest.temperature) * weight;
+                    }
                 }
             }
+
+            particle.temperature = (1.0 - alpha) * t_pic + alpha * t_flip;
+            thermal.update_phase(particle);
+        }
+    }
+
+    fn compute_particle_collisions(&mut self, delta_t: f32, colliders: &[Box<dyn Collider>]) {
+        for particle in &mut self.all_particles {
+            for co in colliders {
+                particle.vel = co.collide(particle.pos, particle.vel, delta_t);
+            }
         }
     }
 
@@ -283,31 +607,137 @@ impl Grid {
         }
     }
 
-    pub fn simulate(&mut self, delta_t: f32, gravity: Vector3<f32>, params: &Params, collision_objects: &Vec<Plane>, collision_cubes: &Vec<Cube>) {
+    /// Pushes particles that still ended up inside a solid collider back onto its surface.
+    fn project_particles(&mut self, colliders: &[Box<dyn Collider>]) {
+        for particle in &mut self.all_particles {
+            for co in colliders {
+                particle.pos = co.project(particle.pos);
+            }
+        }
+    }
+
+    fn emit_particles(&mut self, delta_t: f32) {
+        let mut rng = rand::thread_rng();
+        let resolution = Vector3::new((self.dim_x / self.h) as usize, (self.dim_y / self.h) as usize, (self.dim_z / self.h) as usize);
+        for emitter in self.emitters.iter_mut() {
+            for _ in 0..emitter.count(self.time, delta_t) {
+                let position = emitter.region.sample(&mut rng);
+                let mut particle = Particle::new(position, emitter.particle_mass, resolution, self.h, emitter.vel);
+                particle.body = emitter.body;
+                particle.temperature = emitter.temperature;
+                if let Some(heterogeneity) = &emitter.heterogeneity {
+                    heterogeneity.apply(&mut particle);
+                }
+                self.all_particles.push(particle);
+            }
+        }
+    }
+
+    fn remove_sunk_particles(&mut self) {
+        let sinks = &self.sinks;
+        self.all_particles.retain(|particle| !sinks.iter().any(|sink| sink.contains(particle.pos)));
+    }
+
+    pub fn simulate(&mut self, delta_t: f32, gravity: Vector3<f32>, params: &Params, colliders: &mut [Box<dyn Collider>]) {
+        self.emit_particles(delta_t);
+        self.remove_sunk_particles();
+        self.time += delta_t;
+
         self.reset_grid();
         self.particle_to_grid();
+        if params.multi_field.is_some() {
+            self.particle_to_fields();
+        }
         if self.first_step {
             self.compute_particle_volumes();
             self.first_step = true;
         }
+        if let Some(thermal) = &params.thermal {
+            self.particle_to_grid_heat(thermal);
+            self.compute_heat_diffusion(delta_t, thermal, colliders);
+        }
         self.compute_f_hat_ep(delta_t);
-        self.compute_grid_forces(params.mu_0, params.lambda_0, params.hardening_coefficient);
+        self.compute_grid_forces(params);
 
-        self.compute_grid_velocities(delta_t, collision_objects, collision_cubes);
-        self.update_deformation_gradients(params.critical_compression, params.critical_stretch, delta_t);
+        self.compute_grid_velocities(delta_t, colliders);
+        if let Some(multi_field) = &params.multi_field {
+            self.resolve_field_contact(multi_field.friction);
+        }
+        self.update_deformation_gradients(params, delta_t);
+        if let Some(sintering) = &params.sintering {
+            self.update_sintering(sintering, delta_t);
+        }
         self.update_particle_velocities(params.flip_pic_ration);
+        if let Some(thermal) = &params.thermal {
+            self.update_particle_temperatures(params.flip_pic_ration, thermal);
+        }
 
         for particle in &mut self.all_particles {
             particle.vel += gravity * delta_t;
         }
 
-        self.compute_particle_collisions(delta_t, collision_objects, collision_cubes);
+        self.compute_particle_collisions(delta_t, colliders);
 
         self.update_particle_positions(delta_t);
+        self.project_particles(colliders);
+
+        for sensor in self.sensors.iter_mut() {
+            sensor.finish_step(self.time, delta_t);
+        }
+        for probe in self.probes.iter_mut() {
+            probe.record(self.time, &self.all_particles);
+        }
+    }
+
+    pub fn set_temperature(&mut self, temperature: f32) {
+        for particle in self.all_particles.iter_mut() {
+            particle.temperature = temperature;
+        }
+    }
+
+    /// Adds an emitter whose particles form a new body.
+    pub fn add_emitter(&mut self, mut emitter: Emitter) {
+        emitter.body = self.num_bodies;
+        self.num_bodies += 1;
+        self.emitters.push(emitter);
+    }
+
+    /// Records the load on collider `sensor.collider` every step.
+    pub fn add_force_sensor(&mut self, sensor: ForceSensor) {
+        self.sensors.push(sensor);
+    }
+
+    /// Measures the snow inside `probe.region` every step.
+    pub fn add_probe(&mut self, probe: Probe) {
+        self.probes.push(probe);
+    }
+
+    /// Particles that enter `sink` are deleted.
+    pub fn add_sink(&mut self, sink: Region) {
+        self.sinks.push(sink);
     }
 
-    pub fn create_sphere_uniform_particles(&mut self, center: Vector3<f32>, num_particles: i32, radius: f32, vel: Vector3<f32>) {
+    /// Adds a body sampled at `positions` whose total mass is `density * volume`, shared evenly between its particles.
+    pub fn add_particles(&mut self, positions: &[Vector3<f32>], volume: f32, density: f32, vel: Vector3<f32>, heterogeneity: Option<&Heterogeneity>) {
+        if positions.is_empty() {
+            return;
+        }
+
+        let mass = density * volume / positions.len() as f32;
+        for position in positions {
+            let mut particle = Particle::new(*position, mass, Vector3::new((self.dim_x / self.h) as usize, (self.dim_y / self.h) as usize, (self.dim_z / self.h) as usize), self.h, vel);
+            particle.body = self.num_bodies;
+            if let Some(heterogeneity) = heterogeneity {
+                heterogeneity.apply(&mut particle);
+            }
+            self.all_particles.push(particle);
+        }
+        self.num_bodies += 1;
+    }
+
+    pub fn create_sphere_uniform_particles(&mut self, center: Vector3<f32>, num_particles: i32, radius: f32, density: f32, vel: Vector3<f32>, heterogeneity: Option<&Heterogeneity>) {
         let mut rng = rand::thread_rng();
+        let mut positions = Vec::new();
 
         for _ in 0..num_particles {
             let random_offset = Vector3::new(
@@ -319,26 +749,161 @@ impl Grid {
             if (position - center).norm() > radius {
                 continue;
             }
-            let particle = Particle::new(position, 1.0, Vector3::new((self.dim_x / self.h) as usize, (self.dim_y / self.h) as usize, (self.dim_z / self.h) as usize), self.h, vel);
+            positions.push(position);
+        }
+
+        let volume = 4.0 / 3.0 * PI * radius.powi(3);
+        self.add_particles(&positions, volume, density, vel, heterogeneity);
+        self.reset_grid();
+    }
+    /// Fills `region` with evenly spread particles `spacing * h` apart, using Poisson-disk sampling.
+    pub fn create_poisson_particles(&mut self, region: &Region, spacing: f32, density: f32, vel: Vector3<f32>, heterogeneity: Option<&Heterogeneity>) {
+        let mut rng = rand::thread_rng();
+        let radius = spacing * self.h;
+        let (min, max) = region.aabb();
+        let positions = sampling::poisson_disk(min, max, radius, |p| region.contains(p), &mut rng);
+        let volume = sampling::estimate_volume(min, max, radius / 2.0, |p| region.contains(p));
+        self.add_particles(&positions, volume, density, vel, heterogeneity);
+        self.reset_grid();
+    }
+
+    /// Fills a closed mesh, already placed in grid coordinates, with particles `spacing * h` apart.
+    pub fn create_mesh_particles(&mut self, mesh: &TriangleMesh, spacing: f32, density: f32, vel: Vector3<f32>, heterogeneity: Option<&Heterogeneity>) {
+        if mesh.is_degenerate() {
+            return;
+        }
+
+        let mut rng = rand::thread_rng();
+        let radius = spacing * self.h;
+        let (min, max) = mesh.bounds();
+        let (inside, volume) = mesh.voxelize(radius / 2.0);
+        let positions = sampling::poisson_disk(min, max, radius, inside, &mut rng);
+        self.add_particles(&positions, volume, density, vel, heterogeneity);
+        self.reset_grid();
+    }
+
+    /// Fills a volume in grid coordinates with particles `spacing * h` apart, clipped to the domain.
+    pub fn create_volume_particles(&mut self, volume: &Volume, spacing: f32, density: f32, vel: Vector3<f32>, heterogeneity: Option<&Heterogeneity>) {
+        let mut rng = rand::thread_rng();
+        let radius = spacing * self.h;
+        let (min, max) = volume.bounds();
+        let min = min.sup(&Vector3::zeros());
+        let max = max.inf(&Vector3::new(self.dim_x, self.dim_y, self.dim_z));
+        if sampling::is_empty(min, max) {
+            return;
+        }
+        let positions = sampling::poisson_disk(min, max, radius, |p| volume.contains(p), &mut rng);
+        let enclosed = sampling::estimate_volume(min, max, radius / 2.0, |p| volume.contains(p));
+        self.add_particles(&positions, enclosed, density, vel, heterogeneity);
+        self.reset_grid();
+    }
+
+    /// Fills the voxels of a placed MagicaVoxel model that have a material with particles `spacing * h` apart.
+    /// The whole model is one body, and every palette index shares the mass of its voxels evenly between the
+    /// particles that landed in them.
+    pub fn create_voxel_particles(&mut self, model: &VoxModel, spacing: f32, vel: Vector3<f32>, heterogeneity: Option<&Heterogeneity>) {
+        let mut rng = rand::thread_rng();
+        let radius = spacing * self.h;
+        let (min, max) = model.bounds();
+        let filled = |p: Vector3<f32>| model.palette_index(p).is_some_and(|index| model.material(index).is_some());
+        let positions = sampling::poisson_disk(min, max, radius, filled, &mut rng);
+
+        let voxel_counts = model.counts();
+        let mut particle_counts = [0usize; 256];
+        for position in positions.iter() {
+            particle_counts[model.palette_index(*position).unwrap() as usize] += 1;
+        }
+
+        let resolution = Vector3::new((self.dim_x / self.h) as usize, (self.dim_y / self.h) as usize, (self.dim_z / self.h) as usize);
+        for position in positions {
+            let index = model.palette_index(position).unwrap();
+            let material = model.material(index).unwrap();
+            let volume = voxel_counts[index as usize] as f32 * model.voxel_size.powi(3);
+            let mass = material.density * volume / particle_counts[index as usize] as f32;
+            let mut particle = Particle::new(position, mass, resolution, self.h, vel);
+            particle.body = self.num_bodies;
+            particle.temperature = material.temperature;
+            particle.stiffness = material.stiffness;
+            particle.strength = material.strength;
+            if let Some(heterogeneity) = heterogeneity {
+                heterogeneity.apply(&mut particle);
+            }
             self.all_particles.push(particle);
         }
+        self.num_bodies += 1;
+        self.reset_grid();
+    }
 
+    /// Lays a layer of snow `depth` deep, measured vertically, on top of a heightfield, clipped to the domain.
+    /// Pair it with a `TerrainCollider` on the same heightfield for slope release experiments.
+    pub fn create_snow_layer(&mut self, heightfield: &Heightfield, depth: f32, spacing: f32, density: f32, heterogeneity: Option<&Heterogeneity>) {
+        let mut rng = rand::thread_rng();
+        let radius = spacing * self.h;
+        // Sample with y measured up from the ground, so every column is filled over (ground, ground + depth] however
+        // steep the terrain is. Lifting the samples onto the ground is a shear, which keeps the volume.
+        let min = Vector3::new(heightfield.origin.x.max(0.0), 0.0, heightfield.origin.z.max(0.0));
+        let max = Vector3::new((heightfield.origin.x + heightfield.size.x).min(self.dim_x), depth, (heightfield.origin.z + heightfield.size.y).min(self.dim_z));
+        let lift = |p: Vector3<f32>| Vector3::new(p.x, heightfield.height(p.x, p.z) + p.y, p.z);
+        let inside = |p: Vector3<f32>| {
+            let y = lift(p).y;
+            p.y > 0.0 && p.y <= depth && y >= 0.0 && y <= self.dim_y
+        };
+        let positions: Vec<Vector3<f32>> = sampling::poisson_disk(min, max, radius, inside, &mut rng).into_iter().map(lift).collect();
+        let volume = sampling::estimate_volume(min, max, radius / 2.0, inside);
+        self.add_particles(&positions, volume, density, Vector3::zeros(), heterogeneity);
         self.reset_grid();
     }
-    pub fn create_snowman(&mut self, num_particles: i32, speed: f32) {
+
+    pub fn create_snowman(&mut self, num_particles: i32, density: f32, speed: f32) {
         let radius1 = 0.8;
-        self.create_sphere_uniform_particles(Vector3::new(self.dim_x / 2.0, radius1, self.dim_z / 2.0), num_particles * 2, radius1, Vector3::zeros());
+        self.create_sphere_uniform_particles(Vector3::new(self.dim_x / 2.0, radius1, self.dim_z / 2.0), num_particles * 2, radius1, density, Vector3::zeros(), None);
         let radius2 = 0.4;
-        self.create_sphere_uniform_particles(Vector3::new(self.dim_x / 2.0, 2.0 * radius1 + radius2 - 0.1, self.dim_z / 2.0), num_particles / 2, radius2, Vector3::zeros());
+        self.create_sphere_uniform_particles(Vector3::new(self.dim_x / 2.0, 2.0 * radius1 + radius2 - 0.1, self.dim_z / 2.0), num_particles / 2, radius2, density, Vector3::zeros(), None);
         let radius3 = 0.2;
-        self.create_sphere_uniform_particles(Vector3::new(self.dim_x / 2.0, 2.0 * radius1 + 2.0 * radius2 + radius3 - 0.2, self.dim_z / 2.0), num_particles / 4, radius3, Vector3::zeros());
+        self.create_sphere_uniform_particles(Vector3::new(self.dim_x / 2.0, 2.0 * radius1 + 2.0 * radius2 + radius3 - 0.2, self.dim_z / 2.0), num_particles / 4, radius3, density, Vector3::zeros(), None);
 
         let radius3 = 0.2;
-        self.create_sphere_uniform_particles(Vector3::new(radius3, radius1, self.dim_z / 2.0), num_particles / 4, radius3, Vector3::new(speed, 0.0, 0.0));
+        self.create_sphere_uniform_particles(Vector3::new(radius3, radius1, self.dim_z / 2.0), num_particles / 4, radius3, density, Vector3::new(speed, 0.0, 0.0), None);
 
         // let radius = 0.1;
         // self.create_sphere_uniform_particles(Vector3::new(self.dim_x / 2.0, radius1, self.dim_z / 2.0 + radius1), 5, radius, Vector3::zeros(), Srgba::new(0, 0, 0, 255));
         // self.create_sphere_uniform_particles(Vector3::new(self.dim_x / 2.0, 2.0 * radius1 + radius2 - 0.1, self.dim_z / 2.0 + radius2), 5, radius, Vector3::zeros(), Srgba::new(0, 0, 0, 255));
         self.reset_grid();
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector2;
+    use super::*;
+
+    #[test]
+    fn snow_layer_covers_a_steep_slope() {
+        let (depth, density) = (0.3, 200.0);
+        let layer = |rise: f32| {
+            let mut grid = Grid::new(Vector3::new(16, 16, 16), 0.25);
+            let heightfield = Heightfield::new(vec![0.0, rise, 0.0, rise], Vector2::new(2, 2), Vector3::new(1.0, 0.1, 1.0), Vector2::new(1.0, 1.0));
+            grid.create_snow_layer(&heightfield, depth, 0.4, density, None);
+            (grid, heightfield)
+        };
+        let (flat, _) = layer(0.0);
+        let (steep, slope) = layer(3.5);
+
+        // The same volume of snow lies on both, so it is sampled as densely and weighs the same
+        let count = |grid: &Grid| grid.all_particles.len() as f32;
+        assert!((count(&steep) - count(&flat)).abs() < 0.2 * count(&flat));
+        let mass: f32 = steep.all_particles.iter().map(|particle| particle.mass).sum();
+        assert!((mass - density * depth).abs() < 0.05 * density * depth);
+        for particle in &steep.all_particles {
+            let ground = slope.height(particle.pos.x, particle.pos.z);
+            assert!(particle.pos.y > ground && particle.pos.y <= ground + depth + 1e-5);
+        }
+        // No column of the slope is left bare
+        for i in 0..4 {
+            for k in 0..4 {
+                let column = |x: f32, z: f32| ((x - 1.0) * 4.0) as usize == i && ((z - 1.0) * 4.0) as usize == k;
+                assert!(steep.all_particles.iter().any(|particle| column(particle.pos.x, particle.pos.z)));
+            }
+        }
+    }
 }
\ No newline at end of file
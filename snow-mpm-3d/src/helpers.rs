@@ -1,16 +1,17 @@
-use std::f32::consts::E;
 use nalgebra::{Matrix3, SVD, Vector3};
 use crate::particle::Particle;
+use crate::plasticity::HardeningLaw;
+use crate::thermal::Phase;
 
 pub struct Helpers {}
 
 impl Helpers {
-    fn lame_mu(mu_0: f32, xi: f32, j_p: f32) -> f32 {
-        mu_0 * E.powf(xi * (1.0 - j_p))
+    fn lame_mu(mu_0: f32, xi: f32, j_p: f32, hardening: &HardeningLaw) -> f32 {
+        mu_0 * hardening.factor(xi, j_p)
     }
 
-    fn lame_lambda(lambda_0: f32, xi: f32, j_p: f32) -> f32 {
-        lambda_0 * E.powf(xi * (1.0 - j_p))
+    fn lame_lambda(lambda_0: f32, xi: f32, j_p: f32, hardening: &HardeningLaw) -> f32 {
+        lambda_0 * hardening.factor(xi, j_p)
     }
 
     fn polar_r(f: Matrix3<f32>) -> Matrix3<f32> {
@@ -21,11 +22,25 @@ impl Helpers {
         u * v.adjoint()
     }
 
-    pub fn psi_derivative(mu_0: f32, lambda_0: f32, xi: f32, particle: &Particle) -> Matrix3<f32> {
+    pub fn psi_derivative(mu_0: f32, lambda_0: f32, xi: f32, hardening: &HardeningLaw, particle: &Particle) -> Matrix3<f32> {
         let j_p = particle.def_p_d.determinant();
         let j_e = particle.f_ep_d.determinant();
         let r_e = Helpers::polar_r(particle.f_ep_d);
-        2.0 * Helpers::lame_mu(mu_0, xi, j_p) * (particle.f_ep_d - r_e) + Helpers::lame_lambda(lambda_0, xi, j_p) * (j_e - 1.0) * j_e * particle.f_ep_d.transpose().try_inverse().unwrap()
+        // Water has no shear stiffness, only the volumetric term resists deformation
+        let mu = if particle.phase == Phase::Water { 0.0 } else { Helpers::lame_mu(mu_0, xi, j_p, hardening) };
+        2.0 * mu * (particle.f_ep_d - r_e) + Helpers::lame_lambda(lambda_0, xi, j_p, hardening) * (j_e - 1.0) * j_e * particle.f_ep_d.transpose().try_inverse().unwrap()
+    }
+
+    /// Stress of the energy the Cam-Clay yield surface is written in, whose Kirchhoff stress is
+    /// `mu * dev(J^(-2/3) F F^T) + kappa / 2 * (J^2 - 1) I` (Wolper et al. 2019). The Lame parameters do not harden,
+    /// the yield surface does instead.
+    pub fn cam_clay_psi_derivative(mu: f32, kappa: f32, particle: &Particle) -> Matrix3<f32> {
+        let f = particle.f_ep_d;
+        let j_e = f.determinant();
+        let b_hat = j_e.powf(-2.0 / 3.0) * f * f.transpose();
+        let mu = if particle.phase == Phase::Water { 0.0 } else { mu };
+        let tau = mu * (b_hat - Matrix3::from_diagonal_element(b_hat.trace() / 3.0)) + Matrix3::from_diagonal_element(0.5 * kappa * (j_e * j_e - 1.0));
+        tau * f.transpose().try_inverse().unwrap()
     }
 
     pub fn outer_product(vec1: Vector3<f32>, vec2: Vector3<f32>) -> Matrix3<f32> {
@@ -70,4 +85,25 @@ impl Helpers {
         let dz = c.x * c.y * Helpers::n_d(scaled.z) / h;
         Vector3::new(dx, dy, dz)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cam_clay_stress_matches_the_yield_surface() {
+        let (mu, kappa) = (2.0, 5.0);
+        let mut particle = Particle::new(Vector3::zeros(), 1.0, Vector3::repeat(4), 1.0, Vector3::zeros());
+        particle.f_ep_d = Matrix3::new(1.1, 0.05, 0.0, 0.0, 0.9, 0.0, 0.02, 0.0, 0.95);
+        let j_e = particle.f_ep_d.determinant();
+        let tau = Helpers::cam_clay_psi_derivative(mu, kappa, &particle) * particle.f_ep_d.transpose();
+
+        // The pressure and deviatoric stress that CamClay::project bounds
+        let pressure = -0.5 * kappa * (j_e * j_e - 1.0);
+        assert!((-tau.trace() / 3.0 - pressure).abs() < 1e-5);
+        let b_hat = j_e.powf(-2.0 / 3.0) * particle.f_ep_d * particle.f_ep_d.transpose();
+        let s_hat = mu * (b_hat - Matrix3::from_diagonal_element(b_hat.trace() / 3.0));
+        assert!((tau - Matrix3::from_diagonal_element(tau.trace() / 3.0) - s_hat).norm() < 1e-5);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,213 @@
+use nalgebra::{Matrix4, UnitQuaternion, Vector3};
+use three_d::{ColorMaterial, Context, Gm, Mesh};
+use crate::collider::{Collider, Contact, to_mat4};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+}
+
+impl Keyframe {
+    pub fn new(time: f32, translation: Vector3<f32>, rotation: UnitQuaternion<f32>) -> Self {
+        Keyframe {
+            time,
+            translation,
+            rotation,
+        }
+    }
+}
+
+/// Translation and rotation of a collider relative to its rest pose.
+pub type Pose = (Vector3<f32>, UnitQuaternion<f32>);
+
+/// Keyframes with strictly increasing times, interpolated linearly in translation and spherically in rotation.
+/// With `looping` the trajectory restarts after the last keyframe, otherwise it holds the last pose.
+#[derive(Debug, Clone)]
+pub struct KeyframeTrack {
+    keyframes: Vec<Keyframe>,
+    looping: bool,
+}
+
+impl KeyframeTrack {
+    /// `None` unless there is at least one keyframe and the times are strictly increasing.
+    pub fn new(keyframes: Vec<Keyframe>, looping: bool) -> Option<Self> {
+        if keyframes.is_empty() || keyframes.windows(2).any(|pair| pair[0].time >= pair[1].time) {
+            return None;
+        }
+        Some(KeyframeTrack {
+            keyframes,
+            looping,
+        })
+    }
+
+    pub fn pose(&self, time: f32) -> Pose {
+        let first = self.keyframes[0];
+        let last = self.keyframes[self.keyframes.len() - 1];
+        let duration = last.time - first.time;
+        let time = if self.looping && duration > 0.0 {
+            first.time + (time - first.time).rem_euclid(duration)
+        } else {
+            time
+        };
+
+        if time <= first.time {
+            return (first.translation, first.rotation);
+        }
+        for pair in self.keyframes.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if time <= b.time {
+                let t = (time - a.time) / (b.time - a.time);
+                return (a.translation.lerp(&b.translation, t), a.rotation.slerp(&b.rotation, t));
+            }
+        }
+        (last.translation, last.rotation)
+    }
+}
+
+/// Trajectory of a collider as an offset from its rest pose over time.
+pub enum Motion {
+    Keyframes(KeyframeTrack),
+    /// Pose as a function of the simulation time.
+    Function(Box<dyn Fn(f32) -> Pose>),
+}
+
+impl Motion {
+    pub fn pose(&self, time: f32) -> Pose {
+        match self {
+            Motion::Keyframes(track) => track.pose(time),
+            Motion::Function(function) => function(time),
+        }
+    }
+}
+
+/// Moves any collider along a scripted trajectory. The wrapped collider is described in its rest pose and the
+/// motion rotates it about `pivot` and then translates it. Grid nodes and particles are collided in the rest frame
+/// of the collider so its own friction model applies, with the velocity of the moving surface added back on.
+pub struct KinematicCollider {
+    collider: Box<dyn Collider>,
+    motion: Motion,
+    pivot: Vector3<f32>,
+    time: f32,
+    translation: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+    linear_velocity: Vector3<f32>,
+    angular_velocity: Vector3<f32>,
+}
+
+impl KinematicCollider {
+    /// `delta_t` is the time step of the simulation, used for the velocity of the first step.
+    pub fn new(collider: Box<dyn Collider>, motion: Motion, pivot: Vector3<f32>, delta_t: f32) -> Self {
+        let mut kinematic = KinematicCollider {
+            collider,
+            motion,
+            pivot,
+            time: 0.0,
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            linear_velocity: Vector3::zeros(),
+            angular_velocity: Vector3::zeros(),
+        };
+        kinematic.set_time(0.0, delta_t);
+        kinematic
+    }
+
+    /// Moves to the pose at `time` and takes the velocity from a forward difference over `delta_t`,
+    /// i.e. the motion the collider makes during the coming step.
+    fn set_time(&mut self, time: f32, delta_t: f32) {
+        let (translation, rotation) = self.motion.pose(time);
+        let (next_translation, next_rotation) = self.motion.pose(time + delta_t);
+        self.time = time;
+        self.translation = translation;
+        self.rotation = rotation;
+        self.linear_velocity = (next_translation - translation) / delta_t;
+        self.angular_velocity = (next_rotation * rotation.inverse()).scaled_axis() / delta_t;
+    }
+
+    fn to_rest(&self, position: Vector3<f32>) -> Vector3<f32> {
+        self.pivot + self.rotation.inverse() * (position - self.pivot - self.translation)
+    }
+
+    fn surface_velocity(&self, position: Vector3<f32>) -> Vector3<f32> {
+        self.linear_velocity + self.angular_velocity.cross(&(position - self.pivot - self.translation))
+    }
+}
+
+impl Collider for KinematicCollider {
+    fn signed_distance(&self, position: Vector3<f32>) -> f32 {
+        self.collider.signed_distance(self.to_rest(position))
+    }
+
+    fn contact(&self) -> Contact {
+        self.collider.contact()
+    }
+
+    fn velocity_at(&self, position: Vector3<f32>) -> Vector3<f32> {
+        self.surface_velocity(position) + self.rotation * self.collider.velocity_at(self.to_rest(position))
+    }
+
+    fn temperature(&self) -> Option<f32> {
+        self.collider.temperature()
+    }
+
+    fn collide(&self, position: Vector3<f32>, velocity: Vector3<f32>, delta_t: f32) -> Vector3<f32> {
+        let surface_velocity = self.surface_velocity(position);
+        let rest_velocity = self.rotation.inverse() * (velocity - surface_velocity);
+        let rest_velocity = self.collider.collide(self.to_rest(position), rest_velocity, delta_t);
+        self.rotation * rest_velocity + surface_velocity
+    }
+
+    fn project(&self, position: Vector3<f32>) -> Vector3<f32> {
+        let rest = self.collider.project(self.to_rest(position));
+        self.pivot + self.translation + self.rotation * (rest - self.pivot)
+    }
+
+    fn advance(&mut self, delta_t: f32) {
+        self.collider.advance(delta_t);
+        self.set_time(self.time + delta_t, delta_t);
+    }
+
+    fn get_materials(&self, context: &Context) -> Vec<Gm<Mesh, ColorMaterial>> {
+        let model = Matrix4::new_translation(&(self.pivot + self.translation))
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_translation(&-self.pivot);
+        let model = to_mat4(model);
+        let mut materials = self.collider.get_materials(context);
+        for material in materials.iter_mut() {
+            material.set_transformation(model * material.transformation());
+        }
+        materials
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, x: f32) -> Keyframe {
+        Keyframe::new(time, Vector3::new(x, 0.0, 0.0), UnitQuaternion::identity())
+    }
+
+    #[test]
+    fn keyframe_track_is_validated() {
+        assert!(KeyframeTrack::new(Vec::new(), false).is_none());
+        assert!(KeyframeTrack::new(vec![keyframe(1.0, 0.0), keyframe(1.0, 1.0)], false).is_none());
+        assert!(KeyframeTrack::new(vec![keyframe(1.0, 0.0), keyframe(0.0, 1.0)], false).is_none());
+        assert!(KeyframeTrack::new(vec![keyframe(1.0, 0.0)], true).is_some());
+    }
+
+    #[test]
+    fn keyframe_track_interpolates_and_loops() {
+        let held = KeyframeTrack::new(vec![keyframe(0.0, 0.0), keyframe(2.0, 4.0)], false).unwrap();
+        assert_eq!(held.pose(-1.0).0.x, 0.0);
+        assert_eq!(held.pose(0.5).0.x, 1.0);
+        assert_eq!(held.pose(3.0).0.x, 4.0);
+
+        let looping = KeyframeTrack::new(vec![keyframe(0.0, 0.0), keyframe(2.0, 4.0)], true).unwrap();
+        assert_eq!(looping.pose(2.5).0.x, 1.0);
+
+        let single = KeyframeTrack::new(vec![keyframe(1.0, 3.0)], true).unwrap();
+        assert_eq!(single.pose(5.0).0.x, 3.0);
+    }
+}
\ No newline at end of file
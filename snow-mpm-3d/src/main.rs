@@ -1,22 +1,42 @@
+mod analysis;
 mod particle;
 mod grid;
 mod helpers;
 mod params;
+mod noise;
+mod collider;
+mod domain;
+mod emitter;
+mod kinematic;
+mod mesh;
 mod plane;
+mod plasticity;
+mod probe;
+mod sampling;
+mod rigid_body;
+mod sensor;
+mod terrain;
+mod thermal;
+mod volume;
+mod vox;
 
 use std::f32::consts::PI;
 use nalgebra::Vector3;
 use three_d::{Camera, ClearState, CpuTexture, DirectionalLight, FrameOutput, Mat4, OrbitControl, radians, Srgba, vec3, Window, WindowSettings};
 use three_d_asset::TextureData;
+use crate::collider::Collider;
 use crate::grid::Grid;
 use crate::params::Params;
-use crate::plane::{Cube, Plane};
+use crate::plane::Plane;
+use crate::plasticity::PlasticityModel;
 use three_d_asset::io::Serialize;
 
 pub fn main() {
     let speed = std::env::args().nth(1).expect("no speed given");
     // To integer
     let speed = speed.parse::<f32>().unwrap();
+    // Pass --analysis after the speed to write the deposit maps, the runout and the recorded probes and sensors
+    let write_analysis = std::env::args().skip(2).any(|arg| arg == "--analysis");
 
     let window = Window::new(WindowSettings {
         title: "Snow Simulation".to_string(),
@@ -39,7 +59,9 @@ pub fn main() {
     let light0 = DirectionalLight::new(&context, 1.0, Srgba::WHITE, &vec3(0.0, -0.5, -0.5));
     let light1 = DirectionalLight::new(&context, 1.0, Srgba::WHITE, &vec3(0.0, 0.5, 0.5));
 
-    let young_modulus: f32 = 1.4e5;
+    // Particles weigh density * volume / count, about 0.12 for the snowballs below instead of the unit mass they used
+    // to have, so the stiffness is scaled by the same factor to keep the impacts looking the same as with 1.4e5
+    let young_modulus: f32 = 1.7e4;
     let poisson_ration: f32 = 0.2;
     let hardening_coefficient: f32 = 10.0;
     let critical_compression: f32 = 2.5e-2;
@@ -51,30 +73,80 @@ pub fn main() {
     let dim: Vector3<f32> = Vector3::new(resolution.x as f32, resolution.y as f32, resolution.z as f32);
     let h: f32 = 5.0 / dim.y;
 
+    let density: f32 = 400.0;
+
     let delta_t: f32 = 1e-3;
     let num_particles: i32 = 4000;
     let radius = (0.5 * num_particles as f32 / (16.0 * PI)).cbrt() * h;
 
     let mut grid = Grid::new(resolution, h);
-    let params = Params::new(young_modulus, poisson_ration, hardening_coefficient, critical_compression, critical_stretch, flip_pic_ration);
+    let mut params = Params::new(young_modulus, poisson_ration, hardening_coefficient, critical_compression, critical_stretch, flip_pic_ration);
+    params.plasticity = PlasticityModel::SingularValueClamp;
+    // params.plasticity = PlasticityModel::CamClay(plasticity::CamClay::new(2.36, 0.5, 0.8, -0.04));
+    params.hardening = plasticity::HardeningLaw::Exponential;
+    // params.hardening = plasticity::HardeningLaw::CappedExponential { min_factor: 0.1, max_factor: 10.0 };
+    // params.relaxation_time = Some(1e-2);
+    params.sintering = None;
+    // params.sintering = Some(plasticity::Sintering::new(0.5, 1.2e2, 3.0, 2.0));
+    params.multi_field = None;
+    // params.multi_field = Some(params::MultiField { friction: 0.3 });
+    params.thermal = None;
+    // params.thermal = Some(thermal::Thermal::new(0.0, 3.34e5, 2.09e3, 4.18e3, 0.3, 0.6));
+
+    // Varies the stiffness, strength and density within each body, pass Some(&heterogeneity) instead of None to use it
+    // let heterogeneity = noise::Heterogeneity::new(7, 2.0, 0.1, 0.3, 0.2);
 
-    grid.create_sphere_uniform_particles(Vector3::new(3.0 * grid.dim_x / 2.0, grid.dim_y - 1.5, grid.dim_z) / 2.0, num_particles, radius, Vector3::new(-1.0 * speed, 0.0, 0.0));
-    // grid.create_sphere_uniform_particles(Vector3::new(grid.dim_x, grid.dim_y, grid.dim_z) / 2.0, num_particles, radius, Vector3::new(speed * 2.0, 10.0, 0.0));
-    grid.create_sphere_uniform_particles(Vector3::new(grid.dim_x / 2.0, grid.dim_y, grid.dim_z) / 2.0, num_particles, radius, Vector3::new(speed, 0.0, 0.0));
-    //grid.create_sphere_uniform_particles(Vector3::new(grid.dim_x, grid.dim_y + 0.8, grid.dim_z + 0.8) / 2.0, num_particles, radius, Vector3::new(speed * 6.0, 0.0, 0.0));
-    // grid.create_snowman(num_particles, speed);
+    grid.create_sphere_uniform_particles(Vector3::new(3.0 * grid.dim_x / 2.0, grid.dim_y - 1.5, grid.dim_z) / 2.0, num_particles, radius, density, Vector3::new(-1.0 * speed, 0.0, 0.0), None);
+    // grid.create_sphere_uniform_particles(Vector3::new(grid.dim_x, grid.dim_y, grid.dim_z) / 2.0, num_particles, radius, density, Vector3::new(speed * 2.0, 10.0, 0.0), None);
+    grid.create_sphere_uniform_particles(Vector3::new(grid.dim_x / 2.0, grid.dim_y, grid.dim_z) / 2.0, num_particles, radius, density, Vector3::new(speed, 0.0, 0.0), None);
+    //grid.create_sphere_uniform_particles(Vector3::new(grid.dim_x, grid.dim_y + 0.8, grid.dim_z + 0.8) / 2.0, num_particles, radius, density, Vector3::new(speed * 6.0, 0.0, 0.0), None);
+    // let ball = emitter::Region::new(collider::Shape::Sphere { radius }, Vector3::new(3.0 * grid.dim_x / 2.0, grid.dim_y - 1.5, grid.dim_z) / 2.0, nalgebra::UnitQuaternion::identity()).unwrap();
+    // grid.create_poisson_particles(&ball, 0.5, density, Vector3::new(-1.0 * speed, 0.0, 0.0), Some(&heterogeneity));
+    // A 30 degree slab of snow on a slope and a hollowed igloo with an entrance tunnel
+    // let slab = volume::Volume::Slab { thickness: 0.3 }.transformed(Vector3::new(grid.dim_x / 2.0, grid.dim_y / 2.0, grid.dim_z / 2.0), nalgebra::UnitQuaternion::from_euler_angles(0.0, 0.0, PI / 6.0), 1.0);
+    // grid.create_volume_particles(&slab, 0.5, density, Vector3::zeros(), Some(&heterogeneity));
+    // let dome = volume::Volume::Ellipsoid { radii: Vector3::new(0.8, 0.6, 0.8) }.intersection(volume::Volume::Box { half_extents: Vector3::new(1.0, 0.6, 1.0) }.translated(Vector3::new(0.0, 0.6, 0.0)));
+    // let hollow = volume::Volume::Ellipsoid { radii: Vector3::new(0.6, 0.45, 0.6) }.union(volume::Volume::Cylinder { half_height: 0.5, radius: 0.2 }.rotated(nalgebra::UnitQuaternion::from_euler_angles(PI / 2.0, 0.0, 0.0)).translated(Vector3::new(0.0, 0.1, 0.7)));
+    // let igloo = dome.difference(hollow).translated(Vector3::new(grid.dim_x / 2.0, 0.0, grid.dim_z / 2.0));
+    // grid.create_volume_particles(&igloo, 0.5, density, Vector3::zeros(), Some(&heterogeneity));
+    // let sculpture = mesh::TriangleMesh::load("assets/roof.obj").expect("failed to load snow mesh");
+    // let sculpture = sculpture.transformed(0.5, Vector3::new(grid.dim_x / 2.0, 0.3 * grid.dim_y, grid.dim_z / 2.0), nalgebra::UnitQuaternion::identity());
+    // grid.create_mesh_particles(&sculpture, 0.5, density, Vector3::zeros(), Some(&heterogeneity));
+    // grid.create_snowman(num_particles, density, speed);
+    grid.set_temperature(-5.0);
+    // Snow fort blocked out in MagicaVoxel, 10 voxels per unit, with palette index 2 packed into harder snow. It is
+    // created after the bulk temperature is set so that its particles keep the temperatures of their voxel materials
+    // let fort = vox::VoxFile::load("assets/fort.vox").expect("failed to load voxel model").models.remove(0);
+    // let fort = fort.placed(Vector3::new(0.5, 0.0, 0.5), 0.1)
+    //     .with_default_material(vox::VoxMaterial::new(density, -5.0))
+    //     .with_material(2, vox::VoxMaterial::new(2.0 * density, -5.0).with_material(2.0, 2.0));
+    // grid.create_voxel_particles(&fort, 0.5, Vector3::zeros(), Some(&heterogeneity));
+    // let snowfall = emitter::Region::new(collider::Shape::Box { half_extents: Vector3::new(0.4 * grid.dim_x, 0.02 * grid.dim_y, 0.4 * grid.dim_z) }, Vector3::new(grid.dim_x / 2.0, 0.95 * grid.dim_y, grid.dim_z / 2.0), nalgebra::UnitQuaternion::identity()).unwrap();
+    // grid.add_emitter(emitter::Emitter::new(snowfall, 2000.0, Vector3::new(0.0, -1.0, 0.0), density, (h / 2.0).powi(3), -5.0));
+    // let cannon = emitter::Region::new(collider::Shape::Sphere { radius: 0.05 * grid.dim_x }, Vector3::new(0.1 * grid.dim_x, 0.5 * grid.dim_y, grid.dim_z / 2.0), nalgebra::UnitQuaternion::identity()).unwrap();
+    // grid.add_emitter(emitter::Emitter::new(cannon, 5000.0, Vector3::new(speed, 2.0, 0.0), density, (h / 2.0).powi(3), -5.0).with_window(0.0, Some(0.5)));
+    // grid.add_sink(emitter::Region::new(collider::Shape::Box { half_extents: Vector3::repeat(0.1 * grid.dim_x) }, Vector3::new(0.9 * grid.dim_x, 0.1 * grid.dim_y, grid.dim_z / 2.0), nalgebra::UnitQuaternion::identity()).unwrap());
+    // grid.domain = domain::Domain::walls(collider::Contact::coulomb(0.2));
+    // grid.domain = domain::Domain::new([
+    //     domain::Boundary::Periodic, domain::Boundary::Periodic,
+    //     domain::Boundary::Wall(collider::Contact::coulomb(0.2)), domain::Boundary::Open,
+    //     domain::Boundary::Wall(collider::Contact::coulomb(0.2)), domain::Boundary::Wall(collider::Contact::coulomb(0.2)),
+    // ]);
 
     let model = Mat4::from_translation(vec3(grid.dim_x / 2.0, grid.dim_y / 2.0, grid.dim_z / 2.0));
 
-    let mut collision_planes: Vec<Plane> = Vec::new();
+    let mut colliders: Vec<Box<dyn Collider>> = Vec::new();
 
     let ground_color = Srgba::new(10, 115, 10, 1);
     let origin = Vector3::new(-1.0 * grid.dim_x / 2.0, -1.0 * grid.dim_y / 2.0, -1.0 * grid.dim_z / 2.0);
     let axis_x = Vector3::new(grid.dim_x, 0.0, 0.0);
     let axis_y = Vector3::new(0.0, grid.dim_y, 0.0);
     let axis_z = Vector3::new(0.0, 0.0, grid.dim_z);
-    let ground_rect = Plane::new(origin, axis_x, axis_z, 0.2, Vector3::zeros(), model, ground_color);
-    collision_planes.push(ground_rect);
+    // The planes face the centre of the domain, which is the origin of the model coordinates
+    let ground_rect = Plane::new(origin, axis_x, axis_z, Vector3::zeros(), 0.2, Vector3::zeros(), model, ground_color);
+    // let ground_rect = ground_rect.with_temperature(10.0);
+    // let ground_rect = ground_rect.with_contact(collider::Contact::new(collider::BoundaryCondition::Sticky, 0.0, 0.0));
+    colliders.push(Box::new(ground_rect));
 
     // 4 walls
     let wall_color = Srgba::new(255, 255, 255, 1);
@@ -82,39 +154,86 @@ pub fn main() {
     let axis_x = Vector3::new(grid.dim_x, 0.0, 0.0);
     let axis_y = Vector3::new(0.0, grid.dim_y, 0.0);
     let axis_z = Vector3::new(0.0, 0.0, grid.dim_z);
-    let wall_rect1 = Plane::new(origin, axis_x, axis_y, 0.2, Vector3::zeros(), model, wall_color);
-    // let wall_rect2 = Plane::new(origin, axis_y, axis_z, 0.2, Vector3::zeros(), model, wall_color);
-    // let wall_rect3 = Plane::new(origin + axis_x, axis_y, axis_z, 0.2, Vector3::zeros(), model, wall_color);
-    // let wall_rect4 = Plane::new(origin + axis_z, axis_x, axis_y, 0.2, Vector3::zeros(), model, wall_color);
-    collision_planes.push(wall_rect1);
-    // collision_planes.push(wall_rect2);
-    // collision_planes.push(wall_rect3);
-    // collision_planes.push(wall_rect4);
+    let wall_rect1 = Plane::new(origin, axis_x, axis_y, Vector3::zeros(), 0.2, Vector3::zeros(), model, wall_color);
+    // let wall_rect2 = Plane::new(origin, axis_y, axis_z, Vector3::zeros(), 0.2, Vector3::zeros(), model, wall_color);
+    // let wall_rect3 = Plane::new(origin + axis_x, axis_y, axis_z, Vector3::zeros(), 0.2, Vector3::zeros(), model, wall_color);
+    // let wall_rect4 = Plane::new(origin + axis_z, axis_x, axis_y, Vector3::zeros(), 0.2, Vector3::zeros(), model, wall_color);
+    colliders.push(Box::new(wall_rect1));
+    // Load on the back wall, with the torque taken about its centre, written with --analysis
+    // grid.add_force_sensor(sensor::ForceSensor::new(colliders.len() - 1, Vector3::new(grid.dim_x / 2.0, grid.dim_y / 2.0, 0.0)));
+    // colliders.push(Box::new(wall_rect2));
+    // colliders.push(Box::new(wall_rect3));
+    // colliders.push(Box::new(wall_rect4));
 
     // let wedge_color = Srgba::new(173, 216, 230, 0);
     // let corner = Vector3::new(0.0, -0.05 * grid.dim_y, -grid.dim_z / 2.0);
     // let top_edge = Vector3::new(0.0, 0.0, 0.8 * grid.dim_z);
     // let edge1 = Vector3::new(0.15 * grid.dim_x, -0.15 * grid.dim_y, 0.0);
     // let edge2 = Vector3::new(-0.15 * grid.dim_x, -0.15 * grid.dim_y, 0.0);
-    // let wedge_rect1 = Plane::new(corner, top_edge, edge1, 0.2, Vector3::zeros(), model, wedge_color);
-    // let wedge_rect2 = Plane::new(corner, top_edge, edge2, 0.2, Vector3::zeros(), model, wedge_color);
-    // collision_planes.push(wedge_rect1);
-    // collision_planes.push(wedge_rect2);
-
-    let collision_cubes: Vec<Cube> = Vec::new();
+    // let wedge_rect1 = Plane::new(corner, top_edge, edge1, corner + Vector3::y(), 0.2, Vector3::zeros(), model, wedge_color);
+    // let wedge_rect2 = Plane::new(corner, top_edge, edge2, corner + Vector3::y(), 0.2, Vector3::zeros(), model, wedge_color);
+    // colliders.push(Box::new(wedge_rect1));
+    // colliders.push(Box::new(wedge_rect2));
 
     // let cube_color = Srgba::new(13, 13, 13, 0);
     // let cube_origin = Vector3::new(0.0, -grid.dim_y / 2.0, 0.0);
     // let cube_u = Vector3::new(0.1 * grid.dim_x, 0.0, 0.0);
     // let cube_v = Vector3::new(0.0, 0.1 * grid.dim_y, 0.0);
     // let cube_w = Vector3::new(0.0, 0.0, 0.1 * grid.dim_z);
-    // let cube = Cube::new(cube_origin, cube_u, cube_v, cube_w, 0.2, Vector3::new(0.0, 0.0, 0.0), model, cube_color);
-    // collision_cubes.push(cube);
+    // let cube = plane::Cube::new(cube_origin, cube_u, cube_v, cube_w, 0.2, Vector3::new(0.0, 0.0, 0.0), model, cube_color);
+    // colliders.push(Box::new(cube));
+    // let ramp = plane::Cube::rotated(Vector3::new(0.0, -0.4 * grid.dim_y, 0.0), Vector3::new(0.3 * grid.dim_x, 0.02 * grid.dim_y, 0.3 * grid.dim_z), nalgebra::UnitQuaternion::from_axis_angle(&Vector3::z_axis(), -PI / 8.0), 0.2, Vector3::zeros(), model, cube_color);
+    // colliders.push(Box::new(ramp));
+
+    // let pole_color = Srgba::new(120, 80, 40, 1);
+    // let pole = collider::SdfCollider::new(collider::Shape::Capsule { half_length: 0.2 * grid.dim_y, radius: 0.03 * grid.dim_x }, Vector3::new(grid.dim_x / 2.0, 0.2 * grid.dim_y, grid.dim_z / 2.0), nalgebra::UnitQuaternion::identity(), 0.2, pole_color);
+    // colliders.push(Box::new(pole));
+
+    // let plow_color = Srgba::new(200, 160, 20, 1);
+    // let plow = collider::SdfCollider::new(collider::Shape::Box { half_extents: Vector3::new(0.02 * grid.dim_x, 0.1 * grid.dim_y, 0.3 * grid.dim_z) }, Vector3::new(0.1 * grid.dim_x, 0.1 * grid.dim_y, grid.dim_z / 2.0), nalgebra::UnitQuaternion::identity(), 0.2, plow_color);
+    // let plow_track = kinematic::KeyframeTrack::new(vec![
+    //     kinematic::Keyframe::new(0.0, Vector3::zeros(), nalgebra::UnitQuaternion::identity()),
+    //     kinematic::Keyframe::new(1.0, Vector3::new(0.8 * grid.dim_x, 0.0, 0.0), nalgebra::UnitQuaternion::identity()),
+    // ], false).unwrap();
+    // colliders.push(Box::new(kinematic::KinematicCollider::new(Box::new(plow), kinematic::Motion::Keyframes(plow_track), Vector3::zeros(), delta_t)));
+
+    // let drum_color = Srgba::new(90, 90, 90, 1);
+    // let drum_center = Vector3::new(grid.dim_x, grid.dim_y, grid.dim_z) / 2.0;
+    // let drum = collider::SdfCollider::new(collider::Shape::Cylinder { half_height: 0.3 * grid.dim_z, radius: 0.1 * grid.dim_x }, drum_center, nalgebra::UnitQuaternion::from_axis_angle(&Vector3::x_axis(), PI / 2.0), 0.5, drum_color);
+    // let drum_motion = kinematic::Motion::Function(Box::new(|t| (Vector3::zeros(), nalgebra::UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 2.0 * PI * t))));
+    // colliders.push(Box::new(kinematic::KinematicCollider::new(Box::new(drum), drum_motion, drum_center, delta_t)));
+
+    // let block_color = Srgba::new(100, 60, 30, 1);
+    // let block = rigid_body::RigidBody::new(collider::Shape::Box { half_extents: Vector3::repeat(0.05 * grid.dim_x) }, 700.0, Vector3::new(0.2 * grid.dim_x, 0.1 * grid.dim_y, grid.dim_z / 2.0), nalgebra::UnitQuaternion::identity(), gravity, 0.3, block_color).unwrap();
+    // colliders.push(Box::new(block.with_velocity(Vector3::new(speed, 0.0, 0.0), Vector3::zeros())));
+
+    // let terrain_color = Srgba::new(90, 80, 70, 1);
+    // let slope = terrain::Heightfield::from_png("assets/slope.png", Vector3::zeros(), nalgebra::Vector2::new(grid.dim_x, grid.dim_z), 0.6 * grid.dim_y).expect("failed to load heightmap");
+    // grid.create_snow_layer(&slope, 0.15, 0.5, density, Some(&heterogeneity));
+    // colliders.push(Box::new(terrain::TerrainCollider::new(slope, 0.3, terrain_color)));
+
+    // let roof_color = Srgba::new(150, 40, 30, 1);
+    // let roof_mesh = mesh::TriangleMesh::load_obj("assets/roof.obj").expect("failed to load collider mesh");
+    // let roof = mesh::MeshCollider::new(&roof_mesh, 0.5, Vector3::new(grid.dim_x / 2.0, 0.3 * grid.dim_y, grid.dim_z / 2.0), nalgebra::UnitQuaternion::identity(), h / 2.0, 0.3, roof_color);
+    // colliders.push(Box::new(roof));
+
+    // Snow piling up against the back wall, and the centre of the domain where the snowballs collide, written with --analysis
+    // grid.add_probe(probe::Probe::cuboid("wall", Vector3::new(grid.dim_x / 2.0, grid.dim_y / 2.0, 0.1 * grid.dim_z), Vector3::new(grid.dim_x / 2.0, grid.dim_y / 2.0, 0.1 * grid.dim_z)));
+    // grid.add_probe(probe::Probe::sphere("impact", Vector3::new(grid.dim_x, grid.dim_y, grid.dim_z) / 2.0, 0.1 * grid.dim_x));
+
+    // Runout is measured along x from the left wall, the deposit maps are written when the run ends
+    let mut tracker = write_analysis.then(|| analysis::DepositTracker::new(Vector3::new(0.0, 0.0, grid.dim_z / 2.0), Vector3::x()));
 
     let mut frame = 0;
     let max_frames = 1200;
     window.render_loop(move |mut frame_input| {
         if frame >= max_frames {
+            if let Some(tracker) = &tracker {
+                if let Err(error) = save_analysis(&grid, tracker) {
+                    eprintln!("Failed to write the analysis: {}", error);
+                }
+            }
+
             let mut frame_output = FrameOutput::default();
             frame_output.exit = true;
             return frame_output;
@@ -128,13 +247,12 @@ pub fn main() {
 
         let start = std::time::Instant::now();
 
-        // for co in &mut collision_cubes {
-        //     for plane in &mut co.sides {
-        //         plane.update_position(delta_t);
-        //     }
-        // }
+        collider::advance_colliders(&mut colliders, delta_t);
 
-        grid.simulate(delta_t, gravity, &params, &collision_planes, &collision_cubes);
+        grid.simulate(delta_t, gravity, &params, &mut colliders);
+        if let Some(tracker) = &mut tracker {
+            tracker.record((frame + 1) as f32 * delta_t, &grid.all_particles);
+        }
         println!("Simulation took {} ms", start.elapsed().as_millis());
 
         let start = std::time::Instant::now();
@@ -145,16 +263,8 @@ pub fn main() {
         }
 
         let mut planes = Vec::new();
-        for plane in &collision_planes {
-            let plane = plane.get_material(&context);
-            planes.push(plane);
-        }
-
-        for cube in &collision_cubes {
-            for plane in &cube.sides {
-                let plane = plane.get_material(&context);
-                planes.push(plane);
-            }
+        for collider in &colliders {
+            planes.extend(collider.get_materials(&context));
         }
 
         let pixels = frame_input
@@ -180,4 +290,20 @@ pub fn main() {
 
         FrameOutput::default()
     });
+}
+
+/// Writes the deposit maps, the runout trajectory and the probe and sensor records to `analysis/`.
+fn save_analysis(grid: &Grid, tracker: &analysis::DepositTracker) -> std::io::Result<()> {
+    std::fs::create_dir_all("analysis")?;
+    let deposit = analysis::DepositMap::new(&grid.all_particles, nalgebra::Vector2::new(64, 64), nalgebra::Vector2::new(grid.dim_x, grid.dim_z), None);
+    deposit.write_csv("analysis/deposit.csv")?;
+    deposit.save_heatmaps("analysis/deposit")?;
+    tracker.write_csv("analysis/trajectory.csv")?;
+    probe::write_probes(&grid.probes, "analysis")?;
+    for (i, sensor) in grid.sensors.iter().enumerate() {
+        sensor.write_csv(format!("analysis/force-{}.csv", i))?;
+    }
+    println!("Peak forces: {:?}", grid.sensors.iter().map(|sensor| (sensor.collider, sensor.peak_force())).collect::<Vec<_>>());
+    println!("Runout: {}", tracker.runout());
+    Ok(())
 }
\ No newline at end of file
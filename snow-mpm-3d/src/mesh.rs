@@ -0,0 +1,564 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::io;
+use std::path::Path;
+use nalgebra::{UnitQuaternion, Vector3};
+use rayon::prelude::*;
+use three_d::{ColorMaterial, Context, CpuMesh, Gm, Indices, Mesh, Positions, Srgba, vec3};
+use crate::collider::{Collider, Contact};
+
+enum PlyProperty {
+    Scalar { name: String, ty: String },
+    List { name: String, count_ty: String, item_ty: String },
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+/// Reads PLY values one at a time, from whitespace separated text or from packed binary.
+struct PlyReader<'a> {
+    body: &'a [u8],
+    offset: usize,
+    ascii: bool,
+    little_endian: bool,
+}
+
+impl<'a> PlyReader<'a> {
+    fn read(&mut self, ty: &str) -> io::Result<f64> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid PLY data: {}", message));
+
+        if self.ascii {
+            while self.offset < self.body.len() && self.body[self.offset].is_ascii_whitespace() {
+                self.offset += 1;
+            }
+            let start = self.offset;
+            while self.offset < self.body.len() && !self.body[self.offset].is_ascii_whitespace() {
+                self.offset += 1;
+            }
+            let token = std::str::from_utf8(&self.body[start..self.offset]).map_err(|_| invalid("not text"))?;
+            return token.parse().map_err(|_| invalid(token));
+        }
+
+        let size = match ty {
+            "char" | "int8" | "uchar" | "uint8" => 1,
+            "short" | "int16" | "ushort" | "uint16" => 2,
+            "int" | "int32" | "uint" | "uint32" | "float" | "float32" => 4,
+            "double" | "float64" => 8,
+            _ => return Err(invalid(ty)),
+        };
+        if self.offset + size > self.body.len() {
+            return Err(invalid("unexpected end of file"));
+        }
+        let mut bytes = [0u8; 8];
+        bytes[..size].copy_from_slice(&self.body[self.offset..self.offset + size]);
+        if !self.little_endian {
+            bytes[..size].reverse();
+        }
+        self.offset += size;
+
+        Ok(match ty {
+            "char" | "int8" => bytes[0] as i8 as f64,
+            "uchar" | "uint8" => bytes[0] as f64,
+            "short" | "int16" => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            "ushort" | "uint16" => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+            "int" | "int32" => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            "uint" | "uint32" => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            "float" | "float32" => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            _ => f64::from_le_bytes(bytes),
+        })
+    }
+}
+
+/// Triangle soup with shared vertices, e.g. loaded from an OBJ or PLY file.
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    pub vertices: Vec<Vector3<f32>>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    /// Reads the `v` and `f` records of a Wavefront OBJ file. Polygons are split into triangle fans,
+    /// texture and normal indices are ignored.
+    pub fn load_obj<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid OBJ line: {}", line));
+
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        for line in fs::read_to_string(path)?.lines() {
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let coords: Vec<f32> = tokens.take(3).map(|t| t.parse().map_err(|_| invalid(line))).collect::<io::Result<_>>()?;
+                    if coords.len() != 3 {
+                        return Err(invalid(line));
+                    }
+                    vertices.push(Vector3::new(coords[0], coords[1], coords[2]));
+                }
+                Some("f") => {
+                    let mut face = Vec::new();
+                    for token in tokens {
+                        let index: i64 = token.split('/').next().unwrap().parse().map_err(|_| invalid(line))?;
+                        // OBJ indices start at 1, negative ones count back from the last vertex
+                        let index = if index < 0 { vertices.len() as i64 + index } else { index - 1 };
+                        if index < 0 || index >= vertices.len() as i64 {
+                            return Err(invalid(line));
+                        }
+                        face.push(index as usize);
+                    }
+                    for i in 1..face.len().saturating_sub(1) {
+                        triangles.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        TriangleMesh::checked(vertices, triangles)
+    }
+
+    /// Reads an ASCII or binary PLY file. Only `x`, `y` and `z` of the `vertex` element and the index lists of the
+    /// `face` element are used, polygons are split into triangle fans.
+    pub fn load_ply<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid PLY header: {}", message));
+
+        let bytes = fs::read(path)?;
+        let marker = b"end_header";
+        let header_end = bytes.windows(marker.len()).position(|w| w == marker).ok_or_else(|| invalid("missing end_header"))?;
+        let body_start = bytes[header_end..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |p| header_end + p + 1);
+        let header = std::str::from_utf8(&bytes[..header_end]).map_err(|_| invalid("not text"))?;
+
+        let mut ascii = true;
+        let mut little_endian = true;
+        let mut elements: Vec<PlyElement> = Vec::new();
+        for line in header.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                ["format", format, ..] => {
+                    ascii = *format == "ascii";
+                    little_endian = *format != "binary_big_endian";
+                }
+                ["element", name, count] => elements.push(PlyElement {
+                    name: name.to_string(),
+                    count: count.parse().map_err(|_| invalid(line))?,
+                    properties: Vec::new(),
+                }),
+                ["property", "list", count_ty, item_ty, name] => elements.last_mut().ok_or_else(|| invalid(line))?.properties.push(PlyProperty::List {
+                    name: name.to_string(),
+                    count_ty: count_ty.to_string(),
+                    item_ty: item_ty.to_string(),
+                }),
+                ["property", ty, name] => elements.last_mut().ok_or_else(|| invalid(line))?.properties.push(PlyProperty::Scalar {
+                    name: name.to_string(),
+                    ty: ty.to_string(),
+                }),
+                _ => {}
+            }
+        }
+
+        let mut reader = PlyReader {
+            body: &bytes[body_start..],
+            offset: 0,
+            ascii,
+            little_endian,
+        };
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        for element in &elements {
+            for _ in 0..element.count {
+                let mut vertex = Vector3::zeros();
+                for property in &element.properties {
+                    match property {
+                        PlyProperty::Scalar { name, ty } => {
+                            let value = reader.read(ty)? as f32;
+                            match name.as_str() {
+                                "x" => vertex.x = value,
+                                "y" => vertex.y = value,
+                                "z" => vertex.z = value,
+                                _ => {}
+                            }
+                        }
+                        PlyProperty::List { name, count_ty, item_ty } => {
+                            let count = reader.read(count_ty)? as usize;
+                            let mut face = Vec::with_capacity(count);
+                            for _ in 0..count {
+                                face.push(reader.read(item_ty)? as usize);
+                            }
+                            if element.name == "face" && (name == "vertex_indices" || name == "vertex_index") {
+                                for i in 1..face.len().saturating_sub(1) {
+                                    triangles.push([face[0], face[i], face[i + 1]]);
+                                }
+                            }
+                        }
+                    }
+                }
+                if element.name == "vertex" {
+                    vertices.push(vertex);
+                }
+            }
+        }
+
+        if triangles.iter().flatten().any(|&index| index >= vertices.len()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "PLY face refers to a missing vertex"));
+        }
+        TriangleMesh::checked(vertices, triangles)
+    }
+
+    /// A mesh without triangles has no inside and no bounds, so the loaders reject it.
+    fn checked(vertices: Vec<Vector3<f32>>, triangles: Vec<[usize; 3]>) -> io::Result<Self> {
+        if vertices.is_empty() || triangles.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "mesh has no vertices or faces"));
+        }
+        Ok(TriangleMesh {
+            vertices,
+            triangles,
+        })
+    }
+
+    /// Loads an OBJ or PLY file depending on its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let extension = path.as_ref().extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("obj") => TriangleMesh::load_obj(path),
+            Some("ply") => TriangleMesh::load_ply(path),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "unsupported mesh format, expected .obj or .ply")),
+        }
+    }
+
+    /// Inside test sampled once on a lattice with spacing `step` over the bounding box, so that filling the mesh
+    /// does not evaluate the winding number for every candidate point. Returns the lookup and the enclosed volume,
+    /// which are empty for a mesh without triangles or that is flat along an axis.
+    pub fn voxelize(&self, step: f32) -> (impl Fn(Vector3<f32>) -> bool + '_, f32) {
+        let (min, max) = self.bounds();
+        let dims = if self.is_degenerate() {
+            Vector3::zeros()
+        } else {
+            ((max - min) / step).map(|c| c.ceil().max(1.0) as usize)
+        };
+        let inside: Vec<bool> = (0..dims.x * dims.y * dims.z).into_par_iter().map(|index| {
+            let i = index % dims.x;
+            let j = (index / dims.x) % dims.y;
+            let k = index / (dims.x * dims.y);
+            self.contains(min + (Vector3::new(i, j, k).cast::<f32>() + Vector3::repeat(0.5)) * step)
+        }).collect();
+        let volume = inside.iter().filter(|&&v| v).count() as f32 * step.powi(3);
+
+        let lookup = move |p: Vector3<f32>| {
+            let cell = (p - min) / step;
+            if cell.iter().any(|&c| c < 0.0) {
+                return false;
+            }
+            let cell = cell.map(|c| c as usize);
+            cell.x < dims.x && cell.y < dims.y && cell.z < dims.z && inside[cell.x + dims.x * (cell.y + dims.y * cell.z)]
+        };
+        (lookup, volume)
+    }
+
+    /// Scales, rotates and then translates every vertex.
+    pub fn transformed(&self, scale: f32, translation: Vector3<f32>, rotation: UnitQuaternion<f32>) -> Self {
+        TriangleMesh {
+            vertices: self.vertices.iter().map(|v| rotation * (v * scale) + translation).collect(),
+            triangles: self.triangles.clone(),
+        }
+    }
+
+    /// Whether the mesh encloses no volume because it has no triangles or no extent along some axis.
+    pub fn is_degenerate(&self) -> bool {
+        let (min, max) = self.bounds();
+        self.triangles.is_empty() || min.iter().zip(max.iter()).any(|(lo, hi)| lo >= hi)
+    }
+
+    pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let mut min = Vector3::repeat(f32::INFINITY);
+        let mut max = Vector3::repeat(f32::NEG_INFINITY);
+        for v in &self.vertices {
+            min = min.inf(v);
+            max = max.sup(v);
+        }
+        (min, max)
+    }
+
+    fn corners(&self, triangle: &[usize; 3]) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>) {
+        (self.vertices[triangle[0]], self.vertices[triangle[1]], self.vertices[triangle[2]])
+    }
+
+    /// Unsigned distance from `p` to the closest triangle.
+    pub fn distance(&self, p: Vector3<f32>) -> f32 {
+        self.triangles.iter().map(|t| {
+            let (a, b, c) = self.corners(t);
+            (closest_point_on_triangle(p, a, b, c) - p).norm()
+        }).fold(f32::INFINITY, f32::min)
+    }
+
+    /// Generalized winding number of the surface around `p`, close to 1 inside a closed mesh and 0 outside.
+    /// Works for meshes with small holes or inconsistent winding where ray casting would fail.
+    pub fn winding_number(&self, p: Vector3<f32>) -> f32 {
+        let mut solid_angle = 0.0;
+        for t in &self.triangles {
+            let (a, b, c) = self.corners(t);
+            let (a, b, c) = (a - p, b - p, c - p);
+            let (la, lb, lc) = (a.norm(), b.norm(), c.norm());
+            let numerator = a.dot(&b.cross(&c));
+            let denominator = la * lb * lc + a.dot(&b) * lc + b.dot(&c) * la + c.dot(&a) * lb;
+            solid_angle += 2.0 * numerator.atan2(denominator);
+        }
+        solid_angle / (4.0 * PI)
+    }
+
+    pub fn contains(&self, p: Vector3<f32>) -> bool {
+        self.winding_number(p).abs() > 0.5
+    }
+
+    pub fn to_cpu_mesh(&self) -> CpuMesh {
+        let mut mesh = CpuMesh {
+            positions: Positions::F32(self.vertices.iter().map(|v| vec3(v.x, v.y, v.z)).collect()),
+            indices: Indices::U32(self.triangles.iter().flat_map(|t| t.iter().map(|&i| i as u32)).collect()),
+            ..Default::default()
+        };
+        mesh.compute_normals();
+        mesh
+    }
+}
+
+/// Ericson, Real-Time Collision Detection, 5.1.5.
+fn closest_point_on_triangle(p: Vector3<f32>, a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> Vector3<f32> {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denominator = 1.0 / (va + vb + vc);
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
+
+/// Collider for arbitrary closed triangle meshes. The mesh is voxelised once into a signed distance field
+/// which is sampled with trilinear interpolation, so queries cost the same regardless of triangle count.
+pub struct MeshCollider {
+    mesh: TriangleMesh,
+    origin: Vector3<f32>,
+    cell_size: f32,
+    dims: [usize; 3],
+    distances: Vec<f32>,
+    contact: Contact,
+    color: Srgba,
+}
+
+impl MeshCollider {
+    /// Places `mesh` in grid coordinates and samples its signed distance every `cell_size`.
+    pub fn new(mesh: &TriangleMesh, scale: f32, translation: Vector3<f32>, rotation: UnitQuaternion<f32>, cell_size: f32, mu: f32, color: Srgba) -> Self {
+        let mesh = mesh.transformed(scale, translation, rotation);
+        let padding = 3.0 * cell_size;
+        let (min, max) = mesh.bounds();
+        let origin = min - Vector3::repeat(padding);
+        let extent = max - min + Vector3::repeat(2.0 * padding);
+        let dims = [
+            (extent.x / cell_size).ceil() as usize + 1,
+            (extent.y / cell_size).ceil() as usize + 1,
+            (extent.z / cell_size).ceil() as usize + 1,
+        ];
+
+        let distances = (0..dims[0] * dims[1] * dims[2]).into_par_iter().map(|index| {
+            let i = index % dims[0];
+            let j = (index / dims[0]) % dims[1];
+            let k = index / (dims[0] * dims[1]);
+            let p = origin + Vector3::new(i as f32, j as f32, k as f32) * cell_size;
+            let distance = mesh.distance(p);
+            if mesh.contains(p) { -distance } else { distance }
+        }).collect();
+
+        MeshCollider {
+            mesh,
+            origin,
+            cell_size,
+            dims,
+            distances,
+            contact: Contact::coulomb(mu),
+            color,
+        }
+    }
+
+    /// Replaces the default separating Coulomb contact.
+    pub fn with_contact(mut self, contact: Contact) -> Self {
+        self.contact = contact;
+        self
+    }
+
+    fn sample(&self, i: usize, j: usize, k: usize) -> f32 {
+        self.distances[i + self.dims[0] * (j + self.dims[1] * k)]
+    }
+}
+
+impl Collider for MeshCollider {
+    fn signed_distance(&self, position: Vector3<f32>) -> f32 {
+        // Clamp into the voxelised box, outside of it add the distance to the box
+        let upper = Vector3::new(self.dims[0] - 1, self.dims[1] - 1, self.dims[2] - 1).cast::<f32>() * self.cell_size;
+        let local = position - self.origin;
+        let clamped = local.zip_map(&upper, |c, u| c.clamp(0.0, u));
+        let outside = (local - clamped).norm();
+
+        let cell = clamped / self.cell_size;
+        let i = (cell.x.floor() as usize).min(self.dims[0] - 2);
+        let j = (cell.y.floor() as usize).min(self.dims[1] - 2);
+        let k = (cell.z.floor() as usize).min(self.dims[2] - 2);
+        let t = cell - Vector3::new(i as f32, j as f32, k as f32);
+
+        let lerp = |a: f32, b: f32, t: f32| a + t * (b - a);
+        let c00 = lerp(self.sample(i, j, k), self.sample(i + 1, j, k), t.x);
+        let c10 = lerp(self.sample(i, j + 1, k), self.sample(i + 1, j + 1, k), t.x);
+        let c01 = lerp(self.sample(i, j, k + 1), self.sample(i + 1, j, k + 1), t.x);
+        let c11 = lerp(self.sample(i, j + 1, k + 1), self.sample(i + 1, j + 1, k + 1), t.x);
+        lerp(lerp(c00, c10, t.y), lerp(c01, c11, t.y), t.z) + outside
+    }
+
+    fn contact(&self) -> Contact {
+        self.contact
+    }
+
+    fn get_materials(&self, context: &Context) -> Vec<Gm<Mesh, ColorMaterial>> {
+        vec![Gm::new(Mesh::new(context, &self.mesh.to_cpu_mesh()), ColorMaterial {
+            color: self.color,
+            ..Default::default()
+        })]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("snow-mpm-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn meshes_without_faces_are_rejected() {
+        let obj = write_temp("empty.obj", b"# nothing here\n");
+        assert_eq!(TriangleMesh::load_obj(&obj).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let obj = write_temp("points.obj", b"v 0 0 0\nv 1 0 0\nv 0 1 0\n");
+        assert_eq!(TriangleMesh::load_obj(&obj).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let ply = write_temp("empty.ply", b"ply\nformat ascii 1.0\nelement vertex 0\nproperty float x\nproperty float y\nproperty float z\nelement face 0\nproperty list uchar int vertex_indices\nend_header\n");
+        assert_eq!(TriangleMesh::load_ply(&ply).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+    const CUBE_VERTICES: [[f32; 3]; 8] = [
+        [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0],
+        [0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0],
+    ];
+    /// Outward facing quads, split into two triangles each when loaded.
+    const CUBE_FACES: [[u32; 4]; 6] = [
+        [0, 3, 2, 1], [4, 5, 6, 7], [0, 1, 5, 4], [2, 3, 7, 6], [0, 4, 7, 3], [1, 2, 6, 5],
+    ];
+    const PLY_HEADER_PROPERTIES: &str = "element vertex 8\nproperty float x\nproperty float y\nproperty float z\nelement face 6\nproperty list uchar int vertex_indices\nend_header\n";
+
+    fn assert_cube(mesh: &TriangleMesh) {
+        assert_eq!(mesh.vertices.len(), 8);
+        for (vertex, expected) in mesh.vertices.iter().zip(CUBE_VERTICES.iter()) {
+            assert_eq!(*vertex, Vector3::from(*expected));
+        }
+        assert_eq!(mesh.triangles.len(), 12);
+        for (quad, triangles) in CUBE_FACES.iter().zip(mesh.triangles.chunks(2)) {
+            let quad = quad.map(|index| index as usize);
+            assert_eq!(triangles[0], [quad[0], quad[1], quad[2]]);
+            assert_eq!(triangles[1], [quad[0], quad[2], quad[3]]);
+        }
+        assert!(mesh.contains(Vector3::repeat(0.5)));
+        assert!(!mesh.contains(Vector3::repeat(1.5)));
+    }
+
+    #[test]
+    fn ascii_ply_cube_round_trips() {
+        let mut ply = format!("ply\nformat ascii 1.0\n{}", PLY_HEADER_PROPERTIES);
+        for vertex in CUBE_VERTICES {
+            ply += &format!("{} {} {}\n", vertex[0], vertex[1], vertex[2]);
+        }
+        for face in CUBE_FACES {
+            ply += &format!("4 {} {} {} {}\n", face[0], face[1], face[2], face[3]);
+        }
+        assert_cube(&TriangleMesh::load_ply(write_temp("cube-ascii.ply", ply.as_bytes())).unwrap());
+    }
+
+    #[test]
+    fn binary_little_endian_ply_cube_round_trips() {
+        let mut ply = format!("ply\nformat binary_little_endian 1.0\n{}", PLY_HEADER_PROPERTIES).into_bytes();
+        for vertex in CUBE_VERTICES {
+            for coordinate in vertex {
+                ply.extend_from_slice(&coordinate.to_le_bytes());
+            }
+        }
+        for face in CUBE_FACES {
+            ply.push(4);
+            for index in face {
+                ply.extend_from_slice(&(index as i32).to_le_bytes());
+            }
+        }
+        assert_cube(&TriangleMesh::load_ply(write_temp("cube-binary.ply", &ply)).unwrap());
+    }
+
+    #[test]
+    fn ply_faces_must_refer_to_vertices() {
+        let mut ply = format!("ply\nformat ascii 1.0\n{}", PLY_HEADER_PROPERTIES);
+        for vertex in CUBE_VERTICES {
+            ply += &format!("{} {} {}\n", vertex[0], vertex[1], vertex[2]);
+        }
+        for _ in CUBE_FACES {
+            ply += "3 0 1 8\n";
+        }
+        assert_eq!(TriangleMesh::load_ply(write_temp("cube-bad-index.ply", ply.as_bytes())).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn degenerate_meshes_voxelize_to_nothing() {
+        let flat = TriangleMesh {
+            vertices: vec![Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0)],
+            triangles: vec![[0, 1, 2]],
+        };
+        assert!(flat.is_degenerate());
+        let (inside, volume) = flat.voxelize(0.1);
+        assert_eq!(volume, 0.0);
+        assert!(!inside(Vector3::new(0.1, 0.0, 0.1)));
+
+        let empty = TriangleMesh {
+            vertices: Vec::new(),
+            triangles: Vec::new(),
+        };
+        assert!(empty.is_degenerate());
+        assert_eq!(empty.voxelize(0.1).1, 0.0);
+    }
+}
\ No newline at end of file
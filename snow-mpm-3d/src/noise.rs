@@ -0,0 +1,101 @@
+use nalgebra::Vector3;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use crate::particle::Particle;
+
+/// Seeded 3D Perlin gradient noise, values roughly in `[-1, 1]`.
+pub struct Noise {
+    perm: [usize; 512],
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut table: Vec<usize> = (0..256).collect();
+        table.shuffle(&mut rng);
+
+        let mut perm = [0; 512];
+        for i in 0..512 {
+            perm[i] = table[i % 256];
+        }
+        Noise { perm }
+    }
+
+    fn fade(t: f32) -> f32 {
+        t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+    }
+
+    fn lerp(t: f32, a: f32, b: f32) -> f32 {
+        a + t * (b - a)
+    }
+
+    fn gradient(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+        let h = hash & 15;
+        let u = if h < 8 { x } else { y };
+        let v = if h < 4 { y } else if h == 12 || h == 14 { x } else { z };
+        (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+    }
+
+    pub fn perlin(&self, position: Vector3<f32>) -> f32 {
+        let floor = position.map(|c| c.floor());
+        let xi = (floor.x as i32 & 255) as usize;
+        let yi = (floor.y as i32 & 255) as usize;
+        let zi = (floor.z as i32 & 255) as usize;
+        let x = position.x - floor.x;
+        let y = position.y - floor.y;
+        let z = position.z - floor.z;
+        let u = Noise::fade(x);
+        let v = Noise::fade(y);
+        let w = Noise::fade(z);
+
+        let p = &self.perm;
+        let a = p[xi] + yi;
+        let aa = p[a] + zi;
+        let ab = p[a + 1] + zi;
+        let b = p[xi + 1] + yi;
+        let ba = p[b] + zi;
+        let bb = p[b + 1] + zi;
+
+        Noise::lerp(w,
+                    Noise::lerp(v,
+                                Noise::lerp(u, Noise::gradient(p[aa], x, y, z), Noise::gradient(p[ba], x - 1.0, y, z)),
+                                Noise::lerp(u, Noise::gradient(p[ab], x, y - 1.0, z), Noise::gradient(p[bb], x - 1.0, y - 1.0, z))),
+                    Noise::lerp(v,
+                                Noise::lerp(u, Noise::gradient(p[aa + 1], x, y, z - 1.0), Noise::gradient(p[ba + 1], x - 1.0, y, z - 1.0)),
+                                Noise::lerp(u, Noise::gradient(p[ab + 1], x, y - 1.0, z - 1.0), Noise::gradient(p[bb + 1], x - 1.0, y - 1.0, z - 1.0))))
+    }
+}
+
+/// Per-body variation of snow properties. Amplitudes are relative, e.g. `0.2` varies a property by about ±20%.
+pub struct Heterogeneity {
+    noise: Noise,
+    pub frequency: f32,
+    pub density_amplitude: f32,
+    pub youngs_modulus_amplitude: f32,
+    pub critical_amplitude: f32,
+}
+
+impl Heterogeneity {
+    pub fn new(seed: u64, frequency: f32, density_amplitude: f32, youngs_modulus_amplitude: f32, critical_amplitude: f32) -> Self {
+        Heterogeneity {
+            noise: Noise::new(seed),
+            frequency,
+            density_amplitude,
+            youngs_modulus_amplitude,
+            critical_amplitude,
+        }
+    }
+
+    fn factor(&self, position: Vector3<f32>, amplitude: f32) -> f32 {
+        (1.0 + amplitude * self.noise.perlin(position)).max(0.05)
+    }
+
+    pub fn apply(&self, particle: &mut Particle) {
+        // Offset the samples so the three properties vary independently
+        let position = particle.pos * self.frequency;
+        particle.mass *= self.factor(position, self.density_amplitude);
+        particle.stiffness *= self.factor(position + Vector3::new(31.7, 11.3, 57.1), self.youngs_modulus_amplitude);
+        particle.strength *= self.factor(position + Vector3::new(73.9, 41.5, 19.3), self.critical_amplitude);
+    }
+}
\ No newline at end of file
@@ -1,3 +1,13 @@
+use crate::plasticity::{HardeningLaw, PlasticityModel, Sintering};
+use crate::thermal::Thermal;
+
+/// Gives every body its own grid velocity field so that separate objects can slide against and separate from each other.
+#[derive(Debug, Clone, Copy)]
+pub struct MultiField {
+    /// Coulomb friction coefficient between bodies in contact.
+    pub friction: f32,
+}
+
 #[derive(Debug)]
 pub struct Params {
     pub hardening_coefficient: f32,
@@ -6,6 +16,13 @@ pub struct Params {
     pub flip_pic_ration: f32,
     pub mu_0: f32,
     pub lambda_0: f32,
+    pub plasticity: PlasticityModel,
+    pub hardening: HardeningLaw,
+    /// Viscoplastic relaxation time, `None` projects onto the yield surface instantly (rate-independent).
+    pub relaxation_time: Option<f32>,
+    pub sintering: Option<Sintering>,
+    pub thermal: Option<Thermal>,
+    pub multi_field: Option<MultiField>,
 }
 
 impl Params {
@@ -20,6 +37,16 @@ impl Params {
             flip_pic_ration,
             mu_0,
             lambda_0,
+            plasticity: PlasticityModel::SingularValueClamp,
+            hardening: HardeningLaw::Exponential,
+            relaxation_time: None,
+            sintering: None,
+            thermal: None,
+            multi_field: None,
         }
     }
+
+    pub fn bulk_modulus(&self) -> f32 {
+        self.lambda_0 + 2.0 / 3.0 * self.mu_0
+    }
 }
\ No newline at end of file
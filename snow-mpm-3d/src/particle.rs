@@ -1,6 +1,7 @@
 use nalgebra::{Matrix3, Vector3};
 use three_d::{ColorMaterial, Context, CpuMaterial, CpuMesh, Gm, Mat4, Mesh, PhysicalMaterial, Srgba};
 use crate::helpers::Helpers;
+use crate::thermal::Phase;
 
 #[derive(Clone)]
 pub struct Particle {
@@ -9,10 +10,19 @@ pub struct Particle {
     resolution: Vector3<usize>,
     pub mass: f32,
     pub vol: f32,
+    pub body: usize,
     h: f32,
     pub def_e_d: Matrix3<f32>,
     pub def_p_d: Matrix3<f32>,
     pub f_ep_d: Matrix3<f32>,
+    pub log_j_p: f32,
+    pub sintering: f32,
+    pub stiffness: f32,
+    pub strength: f32,
+    pub stress: Matrix3<f32>,
+    pub temperature: f32,
+    pub latent_heat: f32,
+    pub phase: Phase,
     pub i1: usize,
     pub i2: usize,
     pub j1: usize,
@@ -32,9 +42,18 @@ impl Particle {
             resolution,
             h,
             vol: 0.0,
+            body: 0,
             def_e_d: Matrix3::identity(),
             def_p_d: Matrix3::identity(),
             f_ep_d: Matrix3::identity(),
+            log_j_p: 0.0,
+            sintering: 0.0,
+            stiffness: 1.0,
+            strength: 1.0,
+            stress: Matrix3::zeros(),
+            temperature: 0.0,
+            latent_heat: 0.0,
+            phase: Phase::Snow,
             i1: 0,
             i2: 0,
             j1: 0,
@@ -58,27 +77,34 @@ impl Particle {
         }
     }
 
-    pub fn compute_neighborhood_bounds(&mut self) {
-        self.i1 = (self.pos.x / self.h - 2.0).ceil() as usize;
-        self.i2 = (self.pos.x / self.h + 2.0).floor() as usize + 1;
-        self.j1 = (self.pos.y / self.h - 2.0).ceil() as usize;
-        self.j2 = (self.pos.y / self.h + 2.0).floor() as usize + 1;
-        self.k1 = (self.pos.z / self.h - 2.0).ceil() as usize;
-        self.k2 = (self.pos.z / self.h + 2.0).floor() as usize + 1;
+    /// Stencil indices are offset by one grid resolution so they can reach below the first node of a periodic
+    /// axis. Along the other axes the stencil is cut off at the grid boundary.
+    pub fn compute_neighborhood_bounds(&mut self, periodic: Vector3<bool>) {
+        let bounds = |pos: f32, resolution: usize, periodic: bool| {
+            let lower = ((pos / self.h - 2.0).ceil() as i64 + resolution as i64) as usize;
+            let upper = ((pos / self.h + 2.0).floor() as i64 + resolution as i64) as usize + 1;
+            if periodic {
+                (lower, upper)
+            } else {
+                (lower.max(resolution), upper.min(2 * resolution))
+            }
+        };
+        (self.i1, self.i2) = bounds(self.pos.x, self.resolution.x, periodic.x);
+        (self.j1, self.j2) = bounds(self.pos.y, self.resolution.y, periodic.y);
+        (self.k1, self.k2) = bounds(self.pos.z, self.resolution.z, periodic.z);
+    }
 
-        self.i1 = self.i1.max(0);
-        self.i2 = self.i2.min(self.resolution.x);
-        self.j1 = self.j1.max(0);
-        self.j2 = self.j2.min(self.resolution.y);
-        self.k1 = self.k1.max(0);
-        self.k2 = self.k2.min(self.resolution.z);
+    /// Grid node a stencil index refers to.
+    pub fn node_index(&self, dest_i: usize, dest_j: usize, dest_k: usize) -> Vector3<usize> {
+        Vector3::new(dest_i % self.resolution.x, dest_j % self.resolution.y, dest_k % self.resolution.z)
     }
 
     pub fn compute_b_spline_d(&mut self) {
         for dest_i in self.i1..self.i2 {
             for dest_j in self.j1..self.j2 {
                 for dest_k in self.k1..self.k2 {
-                    let scaled = self.pos / self.h - Vector3::new(dest_i as f32, dest_j as f32, dest_k as f32);
+                    let node = Vector3::new(dest_i, dest_j, dest_k).cast::<f32>() - self.resolution.cast::<f32>();
+                    let scaled = self.pos / self.h - node;
                     self.set_b_spline_val(dest_i - self.i1, dest_j - self.j1, dest_k - self.k1, Helpers::b_spline(scaled));
                     self.set_b_spline_d_val(dest_i - self.i1, dest_j - self.j1, dest_k - self.k1, Helpers::b_spline_d(scaled, self.h));
                 }
@@ -110,9 +136,13 @@ impl Particle {
 
     pub fn get_sphere_material(&self, context: &Context) -> Gm<Mesh, ColorMaterial> {
         let pos = three_d::Vector3::new(self.pos.x, self.pos.y, self.pos.z);
+        let color = match self.phase {
+            Phase::Snow => Srgba::new(255, 255, 255, 255),
+            Phase::Water => Srgba::new(64, 128, 255, 255),
+        };
         let mut sphere = Gm::new(
             Mesh::new(context, &CpuMesh::sphere(8)), ColorMaterial {
-                color: Srgba::new(255, 255, 255, 255),
+                color,
                 texture: None,
                 render_states: Default::default(),
                 is_transparent: false,
@@ -120,4 +150,4 @@ impl Particle {
         sphere.set_transformation(Mat4::from_translation(pos) * Mat4::from_scale(0.04));
         sphere
     }
-}
+}
\ No newline at end of file
@@ -1,67 +1,57 @@
-use nalgebra::Vector3;
+use nalgebra::{UnitQuaternion, Vector3};
 use three_d::{ColorMaterial, Context, CpuMesh, Gm, Mat4, Mesh, Positions, Srgba, vec3, vec4};
+use crate::collider::{Collider, Contact};
 
 pub struct Plane {
     o: Vector3<f32>,
     u: Vector3<f32>,
     v: Vector3<f32>,
     normal: Vector3<f32>,
-    mu: f32,
+    contact: Contact,
     vel: Vector3<f32>,
     model: Mat4,
     color: Srgba,
+    temperature: Option<f32>,
 }
 
 impl Plane {
-    pub fn new(o: Vector3<f32>, u: Vector3<f32>, v: Vector3<f32>, mu: f32, vel: Vector3<f32>, model: Mat4, color: Srgba) -> Self {
+    /// Rectangle spanned by `u` and `v` from the corner `o`. The normal points to the side of `outside`, which is
+    /// given in the same model coordinates as `o`, so that the snow on that side has a positive signed distance.
+    pub fn new(o: Vector3<f32>, u: Vector3<f32>, v: Vector3<f32>, outside: Vector3<f32>, mu: f32, vel: Vector3<f32>, model: Mat4, color: Srgba) -> Self {
         if u.dot(&v).abs() > 1e-6 {
             panic!("edge_u and edge_v must be orthogonal");
         }
 
         let normal = u.cross(&v).normalize();
+        let normal = if (outside - o).dot(&normal) < 0.0 { -normal } else { normal };
         Plane {
             o,
             u,
             v,
             normal,
-            mu,
+            contact: Contact::coulomb(mu),
             vel,
             model,
             color,
+            temperature: None,
         }
     }
 
-    pub fn collide(&self, position: Vector3<f32>, velocity: Vector3<f32>, delta_t: f32) -> Vector3<f32> {
-        let vel_rel = velocity - self.vel;
-        let m3d = self.model * vec4(self.o.x, self.o.y, self.o.z, 1.0);
-        let model = Vector3::new(m3d.x, m3d.y, m3d.z);
-        let temp = self.o + self.vel * delta_t;
-        let next_m3d = self.model * vec4(temp.x, temp.y, temp.z, 1.0);
-        let next_model = Vector3::new(next_m3d.x, next_m3d.y, next_m3d.z);
-        let next_pos = position + velocity * delta_t;
+    /// Makes the plane a heat source that holds the grid nodes touching it at `temperature`.
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
 
-        let next_position_origin = next_pos - model;
-        let offset = (position - model).dot(&self.normal);
-        let offset_next = (next_pos - next_model).dot(&self.normal);
+    /// Replaces the default separating Coulomb contact.
+    pub fn with_contact(mut self, contact: Contact) -> Self {
+        self.contact = contact;
+        self
+    }
 
-        if offset.abs() < 1e-3 || offset * offset_next < 0.0 {
-            let next_position_plane = next_position_origin - self.normal * next_position_origin.dot(&self.normal);
-            let proj_u = next_position_plane.dot(&self.u);
-            let proj_v = next_position_plane.dot(&self.v);
-            if proj_u > 0.0 && proj_u < self.u.norm_squared()
-                && proj_v > 0.0 && proj_v < self.v.norm_squared() {
-                let outward_normal = if (position - model).dot(&self.normal) > 0.0 { self.normal } else { -self.normal };
-                let v_n = vel_rel.dot(&outward_normal);
-                let velocity_tangent = vel_rel - outward_normal * v_n;
-                let mag_velocity_tangent = velocity_tangent.norm();
-                return if mag_velocity_tangent <= -self.mu * v_n {
-                    self.vel + Vector3::new(0.0, 0.0, 0.0)
-                } else {
-                    self.vel + ((1.0 + self.mu * v_n / mag_velocity_tangent) * velocity_tangent)
-                };
-            }
-        }
-        velocity
+    fn origin(&self) -> Vector3<f32> {
+        let m3d = self.model * vec4(self.o.x, self.o.y, self.o.z, 1.0);
+        Vector3::new(m3d.x, m3d.y, m3d.z)
     }
 
     pub fn update_position(&mut self, delta_t: f32) {
@@ -100,6 +90,78 @@ impl Plane {
     }
 }
 
+impl Collider for Plane {
+    /// Distance to the nearest point of the rectangle, negative behind it, away from the outside given to `new`.
+    fn signed_distance(&self, position: Vector3<f32>) -> f32 {
+        let origin = self.origin();
+        let position_origin = position - origin;
+        let proj_u = (position_origin.dot(&self.u) / self.u.norm_squared()).clamp(0.0, 1.0);
+        let proj_v = (position_origin.dot(&self.v) / self.v.norm_squared()).clamp(0.0, 1.0);
+        let distance = (position - (origin + self.u * proj_u + self.v * proj_v)).norm();
+        if position_origin.dot(&self.normal) < 0.0 { -distance } else { distance }
+    }
+
+    fn normal(&self, _position: Vector3<f32>) -> Vector3<f32> {
+        self.normal
+    }
+
+    fn contact(&self) -> Contact {
+        self.contact
+    }
+
+    fn velocity_at(&self, _position: Vector3<f32>) -> Vector3<f32> {
+        self.vel
+    }
+
+    fn temperature(&self) -> Option<f32> {
+        self.temperature
+    }
+
+    /// Only nodes within `h` of the rectangle itself, on either side, touch it.
+    fn touches(&self, position: Vector3<f32>, h: f32) -> bool {
+        self.signed_distance(position).abs() < h
+    }
+
+    fn collide(&self, position: Vector3<f32>, velocity: Vector3<f32>, delta_t: f32) -> Vector3<f32> {
+        let vel_rel = velocity - self.vel;
+        let m3d = self.model * vec4(self.o.x, self.o.y, self.o.z, 1.0);
+        let model = Vector3::new(m3d.x, m3d.y, m3d.z);
+        let temp = self.o + self.vel * delta_t;
+        let next_m3d = self.model * vec4(temp.x, temp.y, temp.z, 1.0);
+        let next_model = Vector3::new(next_m3d.x, next_m3d.y, next_m3d.z);
+        let next_pos = position + velocity * delta_t;
+
+        let next_position_origin = next_pos - model;
+        let offset = (position - model).dot(&self.normal);
+        let offset_next = (next_pos - next_model).dot(&self.normal);
+
+        if offset.abs() < 1e-3 || offset * offset_next < 0.0 {
+            let next_position_plane = next_position_origin - self.normal * next_position_origin.dot(&self.normal);
+            let proj_u = next_position_plane.dot(&self.u);
+            let proj_v = next_position_plane.dot(&self.v);
+            if proj_u > 0.0 && proj_u < self.u.norm_squared()
+                && proj_v > 0.0 && proj_v < self.v.norm_squared() {
+                let outward_normal = if (position - model).dot(&self.normal) > 0.0 { self.normal } else { -self.normal };
+                return self.vel + self.contact.respond(vel_rel, outward_normal);
+            }
+        }
+        velocity
+    }
+
+    /// A plane is a surface without an inside, particles behind it are left where they are.
+    fn project(&self, position: Vector3<f32>) -> Vector3<f32> {
+        position
+    }
+
+    fn advance(&mut self, delta_t: f32) {
+        self.update_position(delta_t);
+    }
+
+    fn get_materials(&self, context: &Context) -> Vec<Gm<Mesh, ColorMaterial>> {
+        vec![self.get_material(context)]
+    }
+}
+
 pub struct Cube {
     pub sides: [Plane; 6],
 }
@@ -119,34 +181,140 @@ impl Cube {
         let ou = o + u;
         let ov = o + v;
         let ow = o + w;
+        // Every face points away from the centre of the box
+        let outside = o - (u + v + w);
+        let outside_u = o + 2.0 * u + v + w;
+        let outside_v = o + u + 2.0 * v + w;
+        let outside_w = o + u + v + 2.0 * w;
         let sides = [
-            Plane::new(o, u, v, mu, vel, model, color),
-            Plane::new(o, u, w, mu, vel, model, color),
-            Plane::new(o, v, w, mu, vel, model, color),
-            Plane::new(ou, v, w, mu, vel, model, color),
-            Plane::new(ov, u, w, mu, vel, model, color),
-            Plane::new(ow, u, v, mu, vel, model, color),
+            Plane::new(o, u, v, outside, mu, vel, model, color),
+            Plane::new(o, u, w, outside, mu, vel, model, color),
+            Plane::new(o, v, w, outside, mu, vel, model, color),
+            Plane::new(ou, v, w, outside_u, mu, vel, model, color),
+            Plane::new(ov, u, w, outside_v, mu, vel, model, color),
+            Plane::new(ow, u, v, outside_w, mu, vel, model, color),
         ];
         Cube {
             sides,
         }
     }
 
-    fn collide(&self, position: Vector3<f32>, velocity: Vector3<f32>, delta_t: f32) -> Vector3<f32> {
-        let mut new_velocity = velocity;
-        for face in self.sides.iter() {
-            new_velocity = face.collide(position, new_velocity, delta_t);
+    /// Box centred on `center` whose edges follow the axes turned by `rotation`.
+    pub fn rotated(center: Vector3<f32>, half_extents: Vector3<f32>, rotation: UnitQuaternion<f32>, mu: f32, vel: Vector3<f32>, model: Mat4, color: Srgba) -> Self {
+        let u = rotation * Vector3::new(2.0 * half_extents.x, 0.0, 0.0);
+        let v = rotation * Vector3::new(0.0, 2.0 * half_extents.y, 0.0);
+        let w = rotation * Vector3::new(0.0, 0.0, 2.0 * half_extents.z);
+        Cube::new(center - (u + v + w) / 2.0, u, v, w, mu, vel, model, color)
+    }
+
+    /// Centre, unit edge directions and half extents of the box in grid coordinates.
+    fn frame(&self) -> (Vector3<f32>, [Vector3<f32>; 3], Vector3<f32>) {
+        let edges = [self.sides[0].u, self.sides[0].v, self.sides[1].v];
+        let center = self.sides[0].origin() + (edges[0] + edges[1] + edges[2]) / 2.0;
+        let half_extents = Vector3::new(edges[0].norm(), edges[1].norm(), edges[2].norm()) / 2.0;
+        (center, edges.map(|edge| edge.normalize()), half_extents)
+    }
+
+    fn to_local(position: Vector3<f32>, center: Vector3<f32>, axes: &[Vector3<f32>; 3]) -> Vector3<f32> {
+        let offset = position - center;
+        Vector3::new(offset.dot(&axes[0]), offset.dot(&axes[1]), offset.dot(&axes[2]))
+    }
+
+    pub fn with_temperature(self, temperature: f32) -> Self {
+        Cube {
+            sides: self.sides.map(|face| face.with_temperature(temperature)),
         }
-        new_velocity
     }
 
-    fn is_stationary(&self) -> bool {
-        false
+    pub fn with_contact(self, contact: Contact) -> Self {
+        Cube {
+            sides: self.sides.map(|face| face.with_contact(contact)),
+        }
     }
 
-    fn update_position(&mut self, delta_t: f32) {
+    pub fn update_position(&mut self, delta_t: f32) {
         for face in self.sides.iter_mut() {
             face.update_position(delta_t);
         }
     }
+}
+
+impl Collider for Cube {
+    /// The box is a solid, so points inside it have a negative distance to the nearest face.
+    fn signed_distance(&self, position: Vector3<f32>) -> f32 {
+        let (center, axes, half_extents) = self.frame();
+        let q = Cube::to_local(position, center, &axes).abs() - half_extents;
+        q.map(|c| c.max(0.0)).norm() + q.max().min(0.0)
+    }
+
+    /// Outward normal of the nearest face, or towards the nearest edge or corner from outside.
+    fn normal(&self, position: Vector3<f32>) -> Vector3<f32> {
+        let (center, axes, half_extents) = self.frame();
+        let local = Cube::to_local(position, center, &axes);
+        let q = local.abs() - half_extents;
+        let direction = if q.max() > 0.0 {
+            q.map(|c| c.max(0.0)).normalize()
+        } else {
+            let mut direction = Vector3::zeros();
+            direction[q.imax()] = 1.0;
+            direction
+        };
+        let local_normal = direction.component_mul(&local.map(|c| if c < 0.0 { -1.0 } else { 1.0 }));
+        axes[0] * local_normal.x + axes[1] * local_normal.y + axes[2] * local_normal.z
+    }
+
+    fn contact(&self) -> Contact {
+        self.sides[0].contact
+    }
+
+    fn velocity_at(&self, _position: Vector3<f32>) -> Vector3<f32> {
+        self.sides[0].vel
+    }
+
+    fn temperature(&self) -> Option<f32> {
+        self.sides[0].temperature
+    }
+
+    fn advance(&mut self, delta_t: f32) {
+        self.update_position(delta_t);
+    }
+
+    fn get_materials(&self, context: &Context) -> Vec<Gm<Mesh, ColorMaterial>> {
+        self.sides.iter().map(|face| face.get_material(context)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plane_heats_only_nodes_near_its_rectangle() {
+        let plane = Plane::new(Vector3::zeros(), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), Vector3::y(), 0.0, Vector3::zeros(), Mat4::from_scale(1.0), Srgba::WHITE)
+            .with_temperature(10.0);
+        let h = 0.1;
+        assert!(plane.touches(Vector3::new(0.5, 0.05, -0.5), h));
+        assert!(plane.touches(Vector3::new(0.5, -0.05, -0.5), h));
+        assert!(!plane.touches(Vector3::new(0.5, -2.0, -0.5), h));
+        assert!(!plane.touches(Vector3::new(3.0, 0.0, -0.5), h));
+    }
+    #[test]
+    fn default_ground_and_wall_face_the_domain() {
+        // Built like the ground and back wall of the default scene, in a domain of 5 units
+        let dim = 5.0;
+        let model = Mat4::from_translation(vec3(dim / 2.0, dim / 2.0, dim / 2.0));
+        let origin = Vector3::repeat(-dim / 2.0);
+        let axis_x = Vector3::new(dim, 0.0, 0.0);
+        let axis_y = Vector3::new(0.0, dim, 0.0);
+        let axis_z = Vector3::new(0.0, 0.0, dim);
+        let ground = Plane::new(origin, axis_x, axis_z, Vector3::zeros(), 0.2, Vector3::zeros(), model, Srgba::WHITE);
+        let wall = Plane::new(origin, axis_x, axis_y, Vector3::zeros(), 0.2, Vector3::zeros(), model, Srgba::WHITE);
+
+        let snow = Vector3::new(2.5, 0.5, 2.5);
+        assert!((ground.signed_distance(snow) - 0.5).abs() < 1e-5);
+        assert!(ground.signed_distance(Vector3::new(2.5, -0.1, 2.5)) < 0.0);
+        assert_eq!(ground.normal(snow), Vector3::y());
+        assert!((wall.signed_distance(snow) - 2.5).abs() < 1e-5);
+        assert_eq!(wall.normal(snow), Vector3::z());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,308 @@
+use nalgebra::{Matrix3, SVD, Vector2, Vector3};
+
+#[derive(Debug, Clone, Copy)]
+pub enum PlasticityModel {
+    /// Stomakhin et al. 2013: clamp the singular values of the elastic deformation gradient
+    /// to `[1 - critical_compression, 1 + critical_stretch]`.
+    SingularValueClamp,
+    /// Non-associative Cam-Clay return mapping (Gaume et al. 2018, Wolper et al. 2019).
+    CamClay(CamClay),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CamClay {
+    /// Slope of the critical state line (M), controls the internal friction of the snow.
+    pub friction_slope: f32,
+    /// Ratio of tensile to compressive strength (beta), i.e. the cohesion of the snow.
+    pub cohesion: f32,
+    /// How fast the yield surface grows with plastic compaction (xi).
+    pub hardening: f32,
+    /// Log of the plastic volume ratio the snow starts with; negative values mean pre-compacted snow.
+    pub initial_log_j_p: f32,
+}
+
+impl CamClay {
+    pub fn new(friction_slope: f32, cohesion: f32, hardening: f32, initial_log_j_p: f32) -> Self {
+        CamClay {
+            friction_slope,
+            cohesion,
+            hardening,
+            initial_log_j_p,
+        }
+    }
+
+    /// Projects the trial elastic deformation gradient back onto the yield surface.
+    /// `log_j_p` is the particle's plastic volume change since creation and is updated in place.
+    pub fn project(&self, f_e_trial: Matrix3<f32>, log_j_p: &mut f32, mu: f32, kappa: f32) -> Matrix3<f32> {
+        let svd_result = SVD::new(f_e_trial, true, true);
+        let u = svd_result.u.unwrap();
+        let v_t = svd_result.v_t.unwrap();
+        let sigma = svd_result.singular_values;
+
+        let m2 = self.friction_slope * self.friction_slope;
+        let beta = self.cohesion;
+        let alpha = self.initial_log_j_p + *log_j_p;
+        // Keep the tip of the ellipse at a pressure some elastic volume ratio can still produce, `J_e >= 0.01`
+        let p0 = (kappa * (1e-5 + (self.hardening * (-alpha).max(0.0)).sinh())).min(0.5 * kappa * (1.0 - 1e-4));
+        let p_min = beta * p0;
+        // Stress tolerance, relative to the stiffness like the pressures it is compared with
+        let tolerance = 1e-4 * kappa;
+
+        let je_trial = sigma.x * sigma.y * sigma.z;
+        let b_hat_trial = sigma.component_mul(&sigma);
+        let b_hat_trace = b_hat_trial.sum();
+        let b_hat_dev = b_hat_trial - Vector3::repeat(b_hat_trace / 3.0);
+        let s_hat_trial = mu * je_trial.powf(-2.0 / 3.0) * b_hat_dev;
+        let p_trial = -0.5 * kappa * (je_trial * je_trial - 1.0);
+
+        // Cases 1 and 2: beyond the tips of the ellipse, project to a purely volumetric state
+        if p_trial > p0 || p_trial < -p_min {
+            let p_tip = if p_trial > p0 { p0 } else { -p_min };
+            let je_new = (1.0 - 2.0 * p_tip / kappa).sqrt();
+            *log_j_p += (je_trial / je_new).ln();
+            return u * Matrix3::from_diagonal_element(je_new.cbrt()) * v_t;
+        }
+
+        // Case 3: inside the pressure range, check the yield function
+        let y_s_half_coeff = 1.5 * (1.0 + 2.0 * beta);
+        let y_p_half = m2 * (p_trial + p_min) * (p_trial - p0);
+        let y = y_s_half_coeff * s_hat_trial.norm_squared() + y_p_half;
+        if y < tolerance * tolerance {
+            return f_e_trial;
+        }
+
+        // Shrink the deviatoric part back onto the yield surface at the trial pressure. The isochoric part of the new
+        // state must have unit determinant, so its trace is found by Newton's method on `prod(dev + t) = 1`.
+        let s_hat_norm = s_hat_trial.norm();
+        let dev_new = (-y_p_half / y_s_half_coeff).sqrt() / mu * s_hat_trial / s_hat_norm;
+        let mut t = je_trial.powf(-2.0 / 3.0) * b_hat_trace / 3.0;
+        for _ in 0..20 {
+            let d = dev_new.add_scalar(t);
+            let f = d.x * d.y * d.z - 1.0;
+            let f_prime = d.x * d.y + d.y * d.z + d.x * d.z;
+            t -= f / f_prime;
+            if f.abs() < 1e-7 {
+                break;
+            }
+        }
+        let sigma_new = dev_new.add_scalar(t).map(|b| b.max(0.0).sqrt()) * je_trial.cbrt();
+
+        // Non-associative hardening: grow or shrink the ellipse towards the trial state
+        if p0 > tolerance && p_trial < p0 - tolerance && p_trial > tolerance - p_min {
+            let p_c = 0.5 * (1.0 - beta) * p0;
+            let q_trial = 1.5f32.sqrt() * s_hat_norm;
+            let direction = Vector2::new(p_c - p_trial, -q_trial).normalize();
+            let c = m2 * (p_c + p_min) * (p_c - p0);
+            let b = m2 * direction.x * (2.0 * p_c - p0 + p_min);
+            let a = m2 * direction.x * direction.x + (1.0 + 2.0 * beta) * direction.y * direction.y;
+            let discriminant = (b * b - 4.0 * a * c).max(0.0).sqrt();
+            let p1 = p_c + (-b + discriminant) / (2.0 * a) * direction.x;
+            let p2 = p_c + (-b - discriminant) / (2.0 * a) * direction.x;
+            let p_x = if (p_trial - p_c) * (p1 - p_c) > 0.0 { p1 } else { p2 };
+            let je_x = (1.0 - 2.0 * p_x / kappa).sqrt();
+            if je_x > 1e-4 {
+                *log_j_p += (je_trial / je_x).ln();
+            }
+        }
+
+        u * Matrix3::from_diagonal(&sigma_new) * v_t
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum HardeningLaw {
+    /// `exp(xi * (1 - J_p))`, Stomakhin et al. 2013.
+    Exponential,
+    /// Exponential hardening clamped to `[min_factor, max_factor]` so heavily compacted or stretched snow stays bounded.
+    CappedExponential { min_factor: f32, max_factor: f32 },
+    /// `J_p^(-xi)`, grows more slowly than the exponential under strong compaction.
+    PowerLaw,
+    /// Piecewise linear factor over a table of `(J_p, factor)` pairs, held constant outside the table.
+    Table(HardeningTable),
+}
+
+#[derive(Debug, Clone)]
+pub struct HardeningTable {
+    points: Vec<(f32, f32)>,
+}
+
+impl HardeningTable {
+    /// `None` unless there is at least one point and the `J_p` values are strictly increasing.
+    pub fn new(points: Vec<(f32, f32)>) -> Option<Self> {
+        if points.is_empty() || points.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return None;
+        }
+        Some(HardeningTable { points })
+    }
+
+    pub fn factor(&self, j_p: f32) -> f32 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        if j_p <= first.0 {
+            return first.1;
+        }
+        for pair in self.points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if j_p <= b.0 {
+                let t = (j_p - a.0) / (b.0 - a.0);
+                return a.1 + t * (b.1 - a.1);
+            }
+        }
+        last.1
+    }
+}
+
+impl HardeningLaw {
+    /// Factor the initial Lame parameters are scaled by for a particle with plastic volume ratio `j_p`.
+    pub fn factor(&self, xi: f32, j_p: f32) -> f32 {
+        match self {
+            HardeningLaw::Exponential => (xi * (1.0 - j_p)).exp(),
+            HardeningLaw::CappedExponential { min_factor, max_factor } => (xi * (1.0 - j_p)).exp().clamp(*min_factor, *max_factor),
+            HardeningLaw::PowerLaw => j_p.powf(-xi),
+            HardeningLaw::Table(table) => table.factor(j_p),
+        }
+    }
+}
+
+/// Duvaut-Lions viscoplastic blend between the trial and the return-mapped state. With `relaxation_time` set the
+/// stress may exceed the yield surface in proportion to the strain rate instead of being projected instantly.
+pub fn relax(trial: f32, projected: f32, delta_t: f32, relaxation_time: Option<f32>) -> f32 {
+    match relaxation_time {
+        Some(tau) => {
+            let r = delta_t / tau;
+            (trial + r * projected) / (1.0 + r)
+        }
+        None => projected,
+    }
+}
+
+/// Bonds that form between grains of resting snow over time. Each particle carries a sintering degree in `[0, 1]`
+/// that grows with time and compressive pressure, stiffens the snow and widens its critical thresholds, and is
+/// reset when the particle yields by more than `break_strain` in a step and the bonds break.
+#[derive(Debug, Clone, Copy)]
+pub struct Sintering {
+    /// Rate at which bonds form in unloaded snow, per second.
+    pub rate: f32,
+    /// Compressive pressure at which bonds form twice as fast as in unloaded snow.
+    pub reference_pressure: f32,
+    /// Factor the Lame parameters of fully sintered snow are scaled by.
+    pub max_stiffening: f32,
+    /// Factor the critical compression and stretch of fully sintered snow are scaled by.
+    pub max_strengthening: f32,
+    /// Relative change of the elastic deformation by the return mapping below which the bonds hold, so that snow
+    /// resting on the yield surface, which is only nudged back onto it by rounding errors, keeps sintering.
+    pub break_strain: f32,
+}
+
+impl Sintering {
+    pub fn new(rate: f32, reference_pressure: f32, max_stiffening: f32, max_strengthening: f32) -> Self {
+        Sintering {
+            rate,
+            reference_pressure,
+            max_stiffening,
+            max_strengthening,
+            break_strain: 1e-4,
+        }
+    }
+
+    /// Whether projecting the elastic deformation from `trial` to `projected` is a plastic increment large enough to
+    /// break the bonds.
+    pub fn bonds_break(&self, trial: &Matrix3<f32>, projected: &Matrix3<f32>) -> bool {
+        (trial - projected).norm() > self.break_strain * trial.norm()
+    }
+
+    pub fn update(&self, sintering: f32, pressure: f32, delta_t: f32) -> f32 {
+        let rate = self.rate * (1.0 + pressure.max(0.0) / self.reference_pressure);
+        (sintering + delta_t * rate * (1.0 - sintering)).min(1.0)
+    }
+
+    pub fn stiffness_factor(&self, sintering: f32) -> f32 {
+        1.0 + (self.max_stiffening - 1.0) * sintering
+    }
+
+    pub fn strength_factor(&self, sintering: f32) -> f32 {
+        1.0 + (self.max_strengthening - 1.0) * sintering
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MU: f32 = 1.0;
+    const KAPPA: f32 = 1.0;
+
+    fn cam_clay() -> CamClay {
+        CamClay::new(1.0, 0.3, 1.0, -0.1)
+    }
+
+    /// Yield function of `f_e` for the ellipse at `log_j_p`, relative to the size of the ellipse.
+    fn yield_value(cam_clay: &CamClay, f_e: Matrix3<f32>, log_j_p: f32) -> f32 {
+        let sigma = SVD::new(f_e, false, false).singular_values;
+        let alpha = cam_clay.initial_log_j_p + log_j_p;
+        let p0 = KAPPA * (1e-5 + (cam_clay.hardening * (-alpha).max(0.0)).sinh());
+        let p_min = cam_clay.cohesion * p0;
+        let je = sigma.x * sigma.y * sigma.z;
+        let b_hat = sigma.component_mul(&sigma);
+        let s_hat = MU * je.powf(-2.0 / 3.0) * (b_hat - Vector3::repeat(b_hat.sum() / 3.0));
+        let p = -0.5 * KAPPA * (je * je - 1.0);
+        let m2 = cam_clay.friction_slope * cam_clay.friction_slope;
+        let y = 1.5 * (1.0 + 2.0 * cam_clay.cohesion) * s_hat.norm_squared() + m2 * (p + p_min) * (p - p0);
+        y / (m2 * p0 * p0)
+    }
+
+    #[test]
+    fn sheared_state_is_projected_onto_the_ellipse() {
+        let cam_clay = cam_clay();
+        let scale = 0.95f32.cbrt();
+        let f_e_trial = Matrix3::from_diagonal(&Vector3::new(1.3 * scale, scale / 1.3, scale));
+        assert!(yield_value(&cam_clay, f_e_trial, 0.0) > 0.0);
+
+        let mut log_j_p = 0.0;
+        let projected = cam_clay.project(f_e_trial, &mut log_j_p, MU, KAPPA);
+        assert!(yield_value(&cam_clay, projected, 0.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn compressed_state_is_projected_onto_the_tip() {
+        let cam_clay = cam_clay();
+        let f_e_trial = Matrix3::from_diagonal_element(0.8);
+        assert!(yield_value(&cam_clay, f_e_trial, 0.0) > 0.0);
+
+        let mut log_j_p = 0.0;
+        let projected = cam_clay.project(f_e_trial, &mut log_j_p, MU, KAPPA);
+        assert!(yield_value(&cam_clay, projected, 0.0).abs() < 1e-3);
+        assert!(log_j_p < 0.0);
+    }
+
+    #[test]
+    fn hardened_tip_stays_finite() {
+        let cam_clay = CamClay::new(1.0, 0.3, 10.0, -1.0);
+        let mut log_j_p = 0.0;
+        let projected = cam_clay.project(Matrix3::from_diagonal_element(0.5), &mut log_j_p, MU, KAPPA);
+        assert!(projected.iter().all(|v| v.is_finite()));
+        assert!(log_j_p.is_finite());
+    }
+
+    #[test]
+    fn hardening_table_is_validated_and_interpolated() {
+        assert!(HardeningTable::new(Vec::new()).is_none());
+        assert!(HardeningTable::new(vec![(1.0, 1.0), (0.5, 2.0)]).is_none());
+        assert!(HardeningTable::new(vec![(0.5, 1.0), (0.5, 2.0)]).is_none());
+
+        let table = HardeningTable::new(vec![(0.5, 4.0), (1.0, 1.0)]).unwrap();
+        assert_eq!(table.factor(0.25), 4.0);
+        assert_eq!(table.factor(0.75), 2.5);
+        assert_eq!(table.factor(2.0), 1.0);
+    }
+    #[test]
+    fn only_real_yielding_breaks_bonds() {
+        let sintering = Sintering::new(0.5, 1e3, 3.0, 2.0);
+        let trial = Matrix3::from_diagonal(&Vector3::new(1.02, 1.0, 0.99));
+        let rounded = trial + Matrix3::from_diagonal_element(1e-6);
+        let clamped = Matrix3::from_diagonal(&Vector3::new(1.0075, 1.0, 0.99));
+        assert!(!sintering.bonds_break(&trial, &trial));
+        assert!(!sintering.bonds_break(&trial, &rounded));
+        assert!(sintering.bonds_break(&trial, &clamped));
+    }
+}
\ No newline at end of file
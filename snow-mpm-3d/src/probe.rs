@@ -0,0 +1,97 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use nalgebra::{Matrix3, UnitQuaternion, Vector3};
+use crate::collider::Shape;
+use crate::emitter::Region;
+use crate::particle::Particle;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProbeSample {
+    /// Simulation time at the end of the step.
+    pub time: f32,
+    pub count: usize,
+    pub mass: f32,
+    /// Mass-weighted, i.e. the velocity of the centre of mass of the snow inside.
+    pub velocity: Vector3<f32>,
+    /// Total mass over total particle volume, as estimated from the grid density.
+    pub density: f32,
+    /// Plastic volume change, `J_p < 1` where the snow has been compacted.
+    pub j_p: f32,
+    /// Cauchy stress averaged over the particle volumes.
+    pub stress: Matrix3<f32>,
+}
+
+/// A named region of the scene whose snow is measured every step.
+pub struct Probe {
+    pub name: String,
+    pub region: Region,
+    pub samples: Vec<ProbeSample>,
+}
+
+impl Probe {
+    pub fn new(name: &str, region: Region) -> Self {
+        Probe {
+            name: name.to_string(),
+            region,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn sphere(name: &str, center: Vector3<f32>, radius: f32) -> Self {
+        Probe::new(name, Region::new(Shape::Sphere { radius }, center, UnitQuaternion::identity()).expect("spheres are bounded"))
+    }
+
+    /// Axis-aligned box.
+    pub fn cuboid(name: &str, center: Vector3<f32>, half_extents: Vector3<f32>) -> Self {
+        Probe::new(name, Region::new(Shape::Box { half_extents }, center, UnitQuaternion::identity()).expect("boxes are bounded"))
+    }
+
+    pub fn record(&mut self, time: f32, particles: &[Particle]) {
+        let mut count = 0;
+        let mut mass = 0.0;
+        let mut momentum = Vector3::zeros();
+        let mut volume = 0.0;
+        let mut j_p = 0.0;
+        let mut stress = Matrix3::zeros();
+        for particle in particles.iter().filter(|particle| self.region.contains(particle.pos)) {
+            count += 1;
+            mass += particle.mass;
+            momentum += particle.vel * particle.mass;
+            volume += particle.vol;
+            j_p += particle.def_p_d.determinant();
+            stress += particle.stress * particle.vol;
+        }
+
+        self.samples.push(ProbeSample {
+            time,
+            count,
+            mass,
+            velocity: if mass > 0.0 { momentum / mass } else { Vector3::zeros() },
+            density: if volume > 0.0 { mass / volume } else { 0.0 },
+            j_p: if count > 0 { j_p / count as f32 } else { 1.0 },
+            stress: if volume > 0.0 { stress / volume } else { Matrix3::zeros() },
+        });
+    }
+
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "time,count,mass,vel_x,vel_y,vel_z,density,j_p,stress_xx,stress_yy,stress_zz,stress_xy,stress_yz,stress_xz")?;
+        for sample in &self.samples {
+            let s = &sample.stress;
+            writeln!(file, "{},{},{},{},{},{},{},{},{},{},{},{},{},{}", sample.time, sample.count, sample.mass,
+                     sample.velocity.x, sample.velocity.y, sample.velocity.z, sample.density, sample.j_p,
+                     s[(0, 0)], s[(1, 1)], s[(2, 2)], s[(0, 1)], s[(1, 2)], s[(0, 2)])?;
+        }
+        file.flush()
+    }
+}
+
+/// Writes every probe to `<directory>/probe-<name>.csv`.
+pub fn write_probes<P: AsRef<Path>>(probes: &[Probe], directory: P) -> io::Result<()> {
+    fs::create_dir_all(&directory)?;
+    for probe in probes {
+        probe.write_csv(directory.as_ref().join(format!("probe-{}.csv", probe.name)))?;
+    }
+    Ok(())
+}
\ No newline at end of file
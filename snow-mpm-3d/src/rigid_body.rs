@@ -0,0 +1,260 @@
+use std::f32::consts::PI;
+use nalgebra::{Matrix3, Matrix4, UnitQuaternion, Vector3};
+use three_d::{ColorMaterial, Context, Gm, Mesh, Srgba};
+use crate::collider::{Collider, Contact, Shape, to_mat4};
+
+/// A dynamic rigid body that is pushed by the snow. Grid nodes collide with its surface like with any other
+/// collider, and the momentum they lose is applied to the body as an impulse on the next step, together with
+/// gravity. Contacts with the other colliders are resolved with Coulomb friction at a set of surface points.
+pub struct RigidBody {
+    shape: Shape,
+    pub mass: f32,
+    /// Inertia tensor about the centre of mass in the body frame.
+    inertia: Matrix3<f32>,
+    pub position: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub vel: Vector3<f32>,
+    pub angular_vel: Vector3<f32>,
+    gravity: Vector3<f32>,
+    contact: Contact,
+    color: Srgba,
+    impulse: Vector3<f32>,
+    angular_impulse: Vector3<f32>,
+    contact_points: Vec<Vector3<f32>>,
+}
+
+impl RigidBody {
+    /// Returns `None` for a half-space, which has no finite mass.
+    pub fn new(shape: Shape, density: f32, position: Vector3<f32>, rotation: UnitQuaternion<f32>, gravity: Vector3<f32>, mu: f32, color: Srgba) -> Option<Self> {
+        let (mass, inertia) = mass_properties(&shape, density)?;
+        Some(RigidBody {
+            shape,
+            mass,
+            inertia,
+            position,
+            rotation,
+            vel: Vector3::zeros(),
+            angular_vel: Vector3::zeros(),
+            gravity,
+            contact: Contact::coulomb(mu),
+            color,
+            impulse: Vector3::zeros(),
+            angular_impulse: Vector3::zeros(),
+            contact_points: contact_points(&shape),
+        })
+    }
+
+    pub fn with_velocity(mut self, vel: Vector3<f32>, angular_vel: Vector3<f32>) -> Self {
+        self.vel = vel;
+        self.angular_vel = angular_vel;
+        self
+    }
+
+    /// Replaces the default separating Coulomb contact.
+    pub fn with_contact(mut self, contact: Contact) -> Self {
+        self.contact = contact;
+        self
+    }
+
+    fn inverse_inertia(&self) -> Matrix3<f32> {
+        let rotation = self.rotation.to_rotation_matrix();
+        rotation * self.inertia.try_inverse().unwrap() * rotation.transpose()
+    }
+
+    fn apply_impulse(&mut self, offset: Vector3<f32>, impulse: Vector3<f32>) {
+        self.vel += impulse / self.mass;
+        self.angular_vel += self.inverse_inertia() * offset.cross(&impulse);
+    }
+}
+
+/// Mass and body frame inertia of a solid of uniform `density`, or `None` if the shape is unbounded. Capsules are
+/// treated as a cylinder capped by a sphere.
+fn mass_properties(shape: &Shape, density: f32) -> Option<(f32, Matrix3<f32>)> {
+    let properties = match *shape {
+        Shape::Sphere { radius } => {
+            let mass = density * 4.0 / 3.0 * PI * radius.powi(3);
+            (mass, Matrix3::from_diagonal_element(0.4 * mass * radius * radius))
+        }
+        Shape::Box { half_extents: e } => {
+            let mass = density * 8.0 * e.x * e.y * e.z;
+            (mass, Matrix3::from_diagonal(&(Vector3::new(e.y * e.y + e.z * e.z, e.x * e.x + e.z * e.z, e.x * e.x + e.y * e.y) * mass / 3.0)))
+        }
+        Shape::Cylinder { half_height, radius } | Shape::Capsule { half_length: half_height, radius } => {
+            let cylinder = density * PI * radius * radius * 2.0 * half_height;
+            let caps = match *shape {
+                Shape::Capsule { .. } => density * 4.0 / 3.0 * PI * radius.powi(3),
+                _ => 0.0,
+            };
+            let mass = cylinder + caps;
+            let axial = 0.5 * cylinder * radius * radius + 0.4 * caps * radius * radius;
+            let transverse = cylinder * (3.0 * radius * radius + 4.0 * half_height * half_height) / 12.0
+                + caps * (0.4 * radius * radius + half_height * half_height);
+            (mass, Matrix3::from_diagonal(&Vector3::new(transverse, axial, transverse)))
+        }
+        Shape::Torus { major_radius, minor_radius } => {
+            let mass = density * 2.0 * PI * PI * major_radius * minor_radius * minor_radius;
+            let axial = mass * (major_radius * major_radius + 0.75 * minor_radius * minor_radius);
+            let transverse = mass * (0.5 * major_radius * major_radius + 0.625 * minor_radius * minor_radius);
+            (mass, Matrix3::from_diagonal(&Vector3::new(transverse, axial, transverse)))
+        }
+        Shape::HalfSpace => return None,
+    };
+    Some(properties)
+}
+
+/// Points on the surface of `shape`, in the body frame, that are kept out of the other colliders.
+fn contact_points(shape: &Shape) -> Vec<Vector3<f32>> {
+    let mut directions = Vec::new();
+    for x in -1..=1 {
+        for y in -1..=1 {
+            for z in -1..=1 {
+                if x != 0 || y != 0 || z != 0 {
+                    directions.push(Vector3::new(x as f32, y as f32, z as f32));
+                }
+            }
+        }
+    }
+
+    match *shape {
+        Shape::Sphere { radius } => directions.iter().map(|d| d.normalize() * radius).collect(),
+        Shape::Box { half_extents } => directions.iter().map(|d| d.component_mul(&half_extents)).collect(),
+        Shape::Capsule { half_length, radius } => directions.iter().map(|d| {
+            d.normalize() * radius + Vector3::new(0.0, d.y * half_length, 0.0)
+        }).collect(),
+        Shape::Cylinder { half_height, radius } => directions.iter().map(|d| {
+            let radial = Vector3::new(d.x, 0.0, d.z);
+            let radial = if radial.norm() > 0.0 { radial.normalize() * radius } else { radial };
+            radial + Vector3::new(0.0, d.y * half_height, 0.0)
+        }).collect(),
+        Shape::Torus { major_radius, minor_radius } => (0..16).flat_map(|i| {
+            let theta = 2.0 * PI * i as f32 / 16.0;
+            let radial = Vector3::new(theta.cos(), 0.0, theta.sin());
+            [
+                radial * (major_radius + minor_radius),
+                radial * major_radius + Vector3::new(0.0, minor_radius, 0.0),
+                radial * major_radius - Vector3::new(0.0, minor_radius, 0.0),
+            ]
+        }).collect(),
+        Shape::HalfSpace => Vec::new(),
+    }
+}
+
+impl Collider for RigidBody {
+    fn signed_distance(&self, position: Vector3<f32>) -> f32 {
+        self.shape.signed_distance(self.rotation.inverse() * (position - self.position))
+    }
+
+    fn contact(&self) -> Contact {
+        self.contact
+    }
+
+    fn velocity_at(&self, position: Vector3<f32>) -> Vector3<f32> {
+        self.vel + self.angular_vel.cross(&(position - self.position))
+    }
+
+    fn add_impulse(&mut self, position: Vector3<f32>, impulse: Vector3<f32>) {
+        self.impulse += impulse;
+        self.angular_impulse += (position - self.position).cross(&impulse);
+    }
+
+    fn advance(&mut self, delta_t: f32) {
+        self.vel += self.impulse / self.mass + self.gravity * delta_t;
+
+        // Euler's equations in the world frame, with the gyroscopic term integrated explicitly
+        let rotation = self.rotation.to_rotation_matrix();
+        let inertia = rotation * self.inertia * rotation.transpose();
+        let gyroscopic = self.angular_vel.cross(&(inertia * self.angular_vel));
+        self.angular_vel += self.inverse_inertia() * (self.angular_impulse - gyroscopic * delta_t);
+
+        self.impulse = Vector3::zeros();
+        self.angular_impulse = Vector3::zeros();
+
+        self.position += self.vel * delta_t;
+        self.rotation = UnitQuaternion::from_scaled_axis(self.angular_vel * delta_t) * self.rotation;
+    }
+
+    fn resolve_contacts(&mut self, others: &[&dyn Collider]) {
+        for other in others {
+            for i in 0..self.contact_points.len() {
+                let offset = self.rotation * self.contact_points[i];
+                let point = self.position + offset;
+                let depth = -other.signed_distance(point);
+                if depth <= 0.0 {
+                    continue;
+                }
+
+                let normal = other.normal(point);
+                self.position += normal * depth;
+
+                let vel_rel = self.velocity_at(point) - other.velocity_at(point);
+                let v_n = vel_rel.dot(&normal);
+                if v_n >= 0.0 {
+                    continue;
+                }
+
+                // Inelastic normal impulse, then Coulomb friction bounded by the normal impulse
+                let inverse_inertia = self.inverse_inertia();
+                let effective_mass = |direction: Vector3<f32>| {
+                    1.0 / (1.0 / self.mass + direction.dot(&(inverse_inertia * offset.cross(&direction)).cross(&offset)))
+                };
+                let j_n = -v_n * effective_mass(normal);
+                let mut impulse = normal * j_n;
+
+                let vel_tangent = vel_rel - normal * v_n;
+                if vel_tangent.norm() > 0.0 {
+                    let tangent = vel_tangent.normalize();
+                    let mu = (self.contact.dynamic_friction * other.contact().dynamic_friction).sqrt();
+                    let j_t = (vel_tangent.norm() * effective_mass(tangent)).min(mu * j_n);
+                    impulse -= tangent * j_t;
+                }
+                self.apply_impulse(offset, impulse);
+            }
+        }
+    }
+
+    fn get_materials(&self, context: &Context) -> Vec<Gm<Mesh, ColorMaterial>> {
+        let model = Matrix4::new_translation(&self.position) * self.rotation.to_homogeneous();
+        self.shape.meshes().into_iter().map(|(cpu_mesh, local)| {
+            let mut gm = Gm::new(Mesh::new(context, &cpu_mesh), ColorMaterial {
+                color: self.color,
+                ..Default::default()
+            });
+            gm.set_transformation(to_mat4(model * local));
+            gm
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use three_d::{Mat4, vec3};
+    use crate::plane::Plane;
+    use super::*;
+
+    #[test]
+    fn rigid_bodies_must_be_bounded() {
+        let gravity = Vector3::new(0.0, -9.81, 0.0);
+        assert!(RigidBody::new(Shape::HalfSpace, 1.0, Vector3::zeros(), UnitQuaternion::identity(), gravity, 0.3, Srgba::WHITE).is_none());
+
+        let body = RigidBody::new(Shape::Box { half_extents: Vector3::repeat(0.5) }, 2.0, Vector3::zeros(), UnitQuaternion::identity(), gravity, 0.3, Srgba::WHITE).unwrap();
+        assert!((body.mass - 2.0).abs() < 1e-6);
+    }
+    #[test]
+    fn block_settles_on_the_default_ground() {
+        // Ground of the default scene in a domain of 5 units, and the block of the commented example above it
+        let dim = 5.0;
+        let model = Mat4::from_translation(vec3(dim / 2.0, dim / 2.0, dim / 2.0));
+        let ground = Plane::new(Vector3::repeat(-dim / 2.0), Vector3::new(dim, 0.0, 0.0), Vector3::new(0.0, 0.0, dim), Vector3::zeros(), 0.2, Vector3::zeros(), model, Srgba::WHITE);
+        let half_extent = 0.05 * dim;
+        let gravity = Vector3::new(0.0, -9.8, 0.0);
+        let mut block = RigidBody::new(Shape::Box { half_extents: Vector3::repeat(half_extent) }, 700.0, Vector3::new(0.2 * dim, 0.1 * dim, dim / 2.0), UnitQuaternion::identity(), gravity, 0.3, Srgba::WHITE).unwrap();
+
+        let delta_t = 1e-3;
+        for _ in 0..2000 {
+            block.advance(delta_t);
+            block.resolve_contacts(&[&ground]);
+        }
+        assert!((block.position.y - half_extent).abs() < 1e-2);
+        assert!(block.vel.norm() < 1e-1);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,117 @@
+use nalgebra::Vector3;
+use rand::Rng;
+
+/// Blue-noise samples inside the shape given by `inside` within the box `[min, max]`, no two closer than
+/// `radius` (Bridson 2007). Random seeds start new patches so disconnected shapes work. Seeding gives up after as
+/// many misses in a row as the background grid has cells, which leaves an uncovered gap the size of one cell with
+/// probability below `1/e` and larger gaps practically never.
+/// An empty box, i.e. `min` above `max` along some axis, has no samples.
+pub fn poisson_disk(min: Vector3<f32>, max: Vector3<f32>, radius: f32, inside: impl Fn(Vector3<f32>) -> bool, rng: &mut impl Rng) -> Vec<Vector3<f32>> {
+    if is_empty(min, max) {
+        return Vec::new();
+    }
+
+    let attempts = 30;
+    let cell_size = radius / 3f32.sqrt();
+    let dims = ((max - min) / cell_size).map(|c| c.ceil().max(1.0) as usize);
+    let mut cells: Vec<Option<usize>> = vec![None; dims.x * dims.y * dims.z];
+    let cell_of = |p: Vector3<f32>| {
+        let c = ((p - min) / cell_size).map(|c| c.floor().max(0.0) as usize);
+        Vector3::new(c.x.min(dims.x - 1), c.y.min(dims.y - 1), c.z.min(dims.z - 1))
+    };
+    let flat = |c: Vector3<usize>| c.x + dims.x * (c.y + dims.y * c.z);
+
+    let mut samples: Vec<Vector3<f32>> = Vec::new();
+    let mut active = Vec::new();
+    let fits = |p: Vector3<f32>, samples: &[Vector3<f32>], cells: &[Option<usize>]| {
+        let c = cell_of(p);
+        for i in c.x.saturating_sub(2)..(c.x + 3).min(dims.x) {
+            for j in c.y.saturating_sub(2)..(c.y + 3).min(dims.y) {
+                for k in c.z.saturating_sub(2)..(c.z + 3).min(dims.z) {
+                    if let Some(index) = cells[flat(Vector3::new(i, j, k))] {
+                        if (samples[index] - p).norm_squared() < radius * radius {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    };
+
+    let seed_attempts = (dims.x * dims.y * dims.z).max(attempts);
+    let mut failed_seeds = 0;
+    while failed_seeds < seed_attempts {
+        let seed = Vector3::new(rng.gen_range(min.x..=max.x), rng.gen_range(min.y..=max.y), rng.gen_range(min.z..=max.z));
+        if !inside(seed) || !fits(seed, &samples, &cells) {
+            failed_seeds += 1;
+            continue;
+        }
+        failed_seeds = 0;
+        cells[flat(cell_of(seed))] = Some(samples.len());
+        active.push(samples.len());
+        samples.push(seed);
+
+        while let Some(&current) = active.last() {
+            let center = samples[current];
+            let mut found = false;
+            for _ in 0..attempts {
+                // Random point in the spherical shell between radius and 2 * radius
+                let direction = Vector3::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0f32..=1.0));
+                if direction.norm_squared() > 1.0 || direction.norm_squared() < 1e-6 {
+                    continue;
+                }
+                let candidate = center + direction.normalize() * radius * rng.gen_range(1.0..2.0);
+                let in_box = candidate.iter().zip(min.iter().zip(max.iter())).all(|(c, (lo, hi))| c >= lo && c <= hi);
+                if in_box && inside(candidate) && fits(candidate, &samples, &cells) {
+                    cells[flat(cell_of(candidate))] = Some(samples.len());
+                    active.push(samples.len());
+                    samples.push(candidate);
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                active.pop();
+            }
+        }
+    }
+    samples
+}
+
+/// Volume of the shape given by `inside` within `[min, max]`, counted on a lattice with spacing `step`.
+pub fn estimate_volume(min: Vector3<f32>, max: Vector3<f32>, step: f32, inside: impl Fn(Vector3<f32>) -> bool) -> f32 {
+    if is_empty(min, max) {
+        return 0.0;
+    }
+
+    let dims = ((max - min) / step).map(|c| c.ceil().max(1.0) as usize);
+    let mut count = 0;
+    for i in 0..dims.x {
+        for j in 0..dims.y {
+            for k in 0..dims.z {
+                if inside(min + (Vector3::new(i, j, k).cast::<f32>() + Vector3::repeat(0.5)) * step) {
+                    count += 1;
+                }
+            }
+        }
+    }
+    count as f32 * step.powi(3)
+}
+
+/// Whether the box `[min, max]` is empty along some axis.
+pub fn is_empty(min: Vector3<f32>, max: Vector3<f32>) -> bool {
+    min.iter().zip(max.iter()).any(|(lo, hi)| lo > hi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_box_has_no_samples() {
+        let (min, max) = (Vector3::new(0.0, 2.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
+        assert!(poisson_disk(min, max, 0.1, |_| true, &mut rand::thread_rng()).is_empty());
+        assert_eq!(estimate_volume(min, max, 0.1, |_| true), 0.0);
+    }
+}
\ No newline at end of file
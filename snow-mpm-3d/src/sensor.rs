@@ -0,0 +1,86 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use nalgebra::Vector3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ForceSample {
+    /// Simulation time at the end of the step.
+    pub time: f32,
+    pub force: Vector3<f32>,
+    /// Torque about the sensor's reference point.
+    pub torque: Vector3<f32>,
+}
+
+/// Measures the load the snow puts on one collider, e.g. the wall the snowballs hit. Every step it adds up the
+/// momentum the collider takes out of the grid nodes and turns it into the mean force and torque over the step.
+/// The particles take their velocities from those nodes, so like the impulse on rigid bodies it leaves out the
+/// particle collisions afterwards, which would count the same momentum a second time.
+pub struct ForceSensor {
+    /// Index of the collider in the slice passed to `Grid::simulate`.
+    pub collider: usize,
+    pub reference: Vector3<f32>,
+    pub samples: Vec<ForceSample>,
+    impulse: Vector3<f32>,
+    angular_impulse: Vector3<f32>,
+}
+
+impl ForceSensor {
+    pub fn new(collider: usize, reference: Vector3<f32>) -> Self {
+        ForceSensor {
+            collider,
+            reference,
+            samples: Vec::new(),
+            impulse: Vector3::zeros(),
+            angular_impulse: Vector3::zeros(),
+        }
+    }
+
+    pub fn add_impulse(&mut self, position: Vector3<f32>, impulse: Vector3<f32>) {
+        self.impulse += impulse;
+        self.angular_impulse += (position - self.reference).cross(&impulse);
+    }
+
+    pub fn finish_step(&mut self, time: f32, delta_t: f32) {
+        self.samples.push(ForceSample {
+            time,
+            force: self.impulse / delta_t,
+            torque: self.angular_impulse / delta_t,
+        });
+        self.impulse = Vector3::zeros();
+        self.angular_impulse = Vector3::zeros();
+    }
+
+    pub fn peak_force(&self) -> f32 {
+        self.samples.iter().map(|sample| sample.force.norm()).fold(0.0, f32::max)
+    }
+
+    /// Total impulse over the recorded steps.
+    pub fn total_impulse(&self) -> Vector3<f32> {
+        let mut total = Vector3::zeros();
+        let mut previous_time = 0.0;
+        for sample in &self.samples {
+            total += sample.force * (sample.time - previous_time);
+            previous_time = sample.time;
+        }
+        total
+    }
+
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(file, "time,force_x,force_y,force_z,torque_x,torque_y,torque_z")?;
+        for sample in &self.samples {
+            writeln!(file, "{},{},{},{},{},{},{}", sample.time,
+                     sample.force.x, sample.force.y, sample.force.z,
+                     sample.torque.x, sample.torque.y, sample.torque.z)?;
+        }
+        file.flush()
+    }
+}
+
+/// Hands an impulse taken out of the snow by collider `collider` to the sensors watching it.
+pub fn record_impulse(sensors: &mut [ForceSensor], collider: usize, position: Vector3<f32>, impulse: Vector3<f32>) {
+    for sensor in sensors.iter_mut().filter(|sensor| sensor.collider == collider) {
+        sensor.add_impulse(position, impulse);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,223 @@
+use std::io;
+use std::path::Path;
+use nalgebra::{Vector2, Vector3};
+use three_d::{ColorMaterial, Context, CpuMesh, Gm, Indices, Mesh, Positions, Srgba, vec3};
+use three_d_asset::{Texture2D, TextureData};
+use crate::collider::{Collider, Contact};
+
+/// Elevations sampled on a regular lattice over the rectangle from `origin` spanning `size` in x and z. Sample
+/// `(i, j)` is at `origin + (i * size.x / (resolution.x - 1), height, j * size.y / (resolution.y - 1))`.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    /// Heights above `origin.y`, x fastest.
+    pub heights: Vec<f32>,
+    pub resolution: Vector2<usize>,
+    pub origin: Vector3<f32>,
+    pub size: Vector2<f32>,
+}
+
+impl Heightfield {
+    pub fn new(heights: Vec<f32>, resolution: Vector2<usize>, origin: Vector3<f32>, size: Vector2<f32>) -> Self {
+        if resolution.x < 2 || resolution.y < 2 || heights.len() != resolution.x * resolution.y {
+            panic!("a heightfield needs at least 2 x 2 samples and one height per sample");
+        }
+
+        Heightfield {
+            heights,
+            resolution,
+            origin,
+            size,
+        }
+    }
+
+    /// Grayscale image where black is `origin.y` and white is `origin.y + max_height`. Image columns run along x and
+    /// rows along z. Colour images use the mean of their channels.
+    pub fn from_png<P: AsRef<Path>>(path: P, origin: Vector3<f32>, size: Vector2<f32>, max_height: f32) -> io::Result<Self> {
+        let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+        let mut assets = three_d_asset::io::load(&[path.as_ref()]).map_err(|e| invalid(e.to_string()))?;
+        let texture: Texture2D = assets.deserialize(path.as_ref()).map_err(|e| invalid(e.to_string()))?;
+
+        let levels: Vec<f32> = match texture.data {
+            TextureData::RU8(data) => data.iter().map(|&v| v as f32 / 255.0).collect(),
+            TextureData::RgU8(data) => data.iter().map(|v| v[0] as f32 / 255.0).collect(),
+            TextureData::RgbU8(data) => data.iter().map(|v| (v[0] as f32 + v[1] as f32 + v[2] as f32) / 765.0).collect(),
+            TextureData::RgbaU8(data) => data.iter().map(|v| (v[0] as f32 + v[1] as f32 + v[2] as f32) / 765.0).collect(),
+            TextureData::RF32(data) => data,
+            TextureData::RgF32(data) => data.iter().map(|v| v[0]).collect(),
+            TextureData::RgbF32(data) => data.iter().map(|v| (v[0] + v[1] + v[2]) / 3.0).collect(),
+            TextureData::RgbaF32(data) => data.iter().map(|v| (v[0] + v[1] + v[2]) / 3.0).collect(),
+            _ => return Err(invalid("unsupported heightmap pixel format".to_string())),
+        };
+
+        let resolution = Vector2::new(texture.width as usize, texture.height as usize);
+        check_resolution(resolution)?;
+        Ok(Heightfield::new(levels.iter().map(|level| level * max_height).collect(), resolution, origin, size))
+    }
+
+    /// Headerless elevation grid of `resolution` samples, x fastest, either little-endian `f32` or `u16` values
+    /// depending on the file size. Values are multiplied by `scale`.
+    pub fn from_raw<P: AsRef<Path>>(path: P, resolution: Vector2<usize>, origin: Vector3<f32>, size: Vector2<f32>, scale: f32) -> io::Result<Self> {
+        check_resolution(resolution)?;
+        let bytes = std::fs::read(path)?;
+        let count = resolution.x * resolution.y;
+        let heights: Vec<f32> = if bytes.len() == 4 * count {
+            bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) * scale).collect()
+        } else if bytes.len() == 2 * count {
+            bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) as f32 * scale).collect()
+        } else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected {} f32 or u16 elevations", count)));
+        };
+        Ok(Heightfield::new(heights, resolution, origin, size))
+    }
+
+    fn spacing(&self) -> Vector2<f32> {
+        self.size.component_div(&(self.resolution - Vector2::repeat(1)).cast::<f32>())
+    }
+
+    fn sample(&self, i: usize, j: usize) -> f32 {
+        self.heights[i + self.resolution.x * j]
+    }
+
+    /// Bilinearly interpolated elevation, the edge samples extend beyond the rectangle.
+    pub fn height(&self, x: f32, z: f32) -> f32 {
+        let cell = Vector2::new(x - self.origin.x, z - self.origin.z).component_div(&self.spacing());
+        let cell = Vector2::new(
+            cell.x.clamp(0.0, (self.resolution.x - 1) as f32),
+            cell.y.clamp(0.0, (self.resolution.y - 1) as f32),
+        );
+        let i = (cell.x as usize).min(self.resolution.x - 2);
+        let j = (cell.y as usize).min(self.resolution.y - 2);
+        let (tx, tz) = (cell.x - i as f32, cell.y - j as f32);
+
+        let near = self.sample(i, j) * (1.0 - tx) + self.sample(i + 1, j) * tx;
+        let far = self.sample(i, j + 1) * (1.0 - tx) + self.sample(i + 1, j + 1) * tx;
+        self.origin.y + near * (1.0 - tz) + far * tz
+    }
+
+    /// Slope of the surface along x and z.
+    pub fn gradient(&self, x: f32, z: f32) -> Vector2<f32> {
+        let eps = 0.5 * self.spacing();
+        Vector2::new(
+            (self.height(x + eps.x, z) - self.height(x - eps.x, z)) / (2.0 * eps.x),
+            (self.height(x, z + eps.y) - self.height(x, z - eps.y)) / (2.0 * eps.y),
+        )
+    }
+
+    pub fn to_cpu_mesh(&self) -> CpuMesh {
+        let spacing = self.spacing();
+        let mut positions = Vec::with_capacity(self.heights.len());
+        for j in 0..self.resolution.y {
+            for i in 0..self.resolution.x {
+                let x = self.origin.x + i as f32 * spacing.x;
+                let z = self.origin.z + j as f32 * spacing.y;
+                positions.push(vec3(x, self.origin.y + self.sample(i, j), z));
+            }
+        }
+
+        let mut indices = Vec::new();
+        for j in 0..self.resolution.y - 1 {
+            for i in 0..self.resolution.x - 1 {
+                let a = (i + self.resolution.x * j) as u32;
+                let b = a + 1;
+                let c = a + self.resolution.x as u32;
+                let d = c + 1;
+                indices.extend_from_slice(&[a, c, b, b, c, d]);
+            }
+        }
+
+        let mut mesh = CpuMesh {
+            positions: Positions::F32(positions),
+            indices: Indices::U32(indices),
+            ..Default::default()
+        };
+        mesh.compute_normals();
+        mesh
+    }
+}
+
+/// The loaders report a heightfield too small to interpolate as bad data instead of panicking in `Heightfield::new`.
+fn check_resolution(resolution: Vector2<usize>) -> io::Result<()> {
+    if resolution.x < 2 || resolution.y < 2 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("a heightfield needs at least 2 x 2 samples, got {} x {}", resolution.x, resolution.y)));
+    }
+    Ok(())
+}
+
+/// Ground shaped by a heightfield, solid below the surface. Like every collider it is respected by both the grid
+/// collision pass and the particle projection.
+pub struct TerrainCollider {
+    pub heightfield: Heightfield,
+    contact: Contact,
+    color: Srgba,
+}
+
+impl TerrainCollider {
+    pub fn new(heightfield: Heightfield, mu: f32, color: Srgba) -> Self {
+        TerrainCollider {
+            heightfield,
+            contact: Contact::coulomb(mu),
+            color,
+        }
+    }
+
+    /// Replaces the default separating Coulomb contact.
+    pub fn with_contact(mut self, contact: Contact) -> Self {
+        self.contact = contact;
+        self
+    }
+}
+
+impl Collider for TerrainCollider {
+    /// Vertical distance scaled by the cosine of the slope, exact for planar patches.
+    fn signed_distance(&self, position: Vector3<f32>) -> f32 {
+        let gradient = self.heightfield.gradient(position.x, position.z);
+        (position.y - self.heightfield.height(position.x, position.z)) / (1.0 + gradient.norm_squared()).sqrt()
+    }
+
+    fn contact(&self) -> Contact {
+        self.contact
+    }
+
+    fn normal(&self, position: Vector3<f32>) -> Vector3<f32> {
+        let gradient = self.heightfield.gradient(position.x, position.z);
+        Vector3::new(-gradient.x, 1.0, -gradient.y).normalize()
+    }
+
+    fn get_materials(&self, context: &Context) -> Vec<Gm<Mesh, ColorMaterial>> {
+        vec![Gm::new(Mesh::new(context, &self.heightfield.to_cpu_mesh()), ColorMaterial {
+            color: self.color,
+            ..Default::default()
+        })]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_raw(name: &str, heights: &[f32]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("snow-mpm-{}-{}", std::process::id(), name));
+        std::fs::write(&path, heights.iter().flat_map(|h| h.to_le_bytes()).collect::<Vec<u8>>()).unwrap();
+        path
+    }
+
+    #[test]
+    fn raw_heightfields_need_two_by_two_samples() {
+        let path = write_raw("single.raw", &[1.0]);
+        let result = Heightfield::from_raw(&path, Vector2::new(1, 1), Vector3::zeros(), Vector2::new(1.0, 1.0), 1.0);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let path = write_raw("row.raw", &[1.0, 2.0, 3.0]);
+        let result = Heightfield::from_raw(&path, Vector2::new(3, 1), Vector3::zeros(), Vector2::new(1.0, 1.0), 1.0);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn raw_heightfield_is_interpolated() {
+        let path = write_raw("ramp.raw", &[0.0, 1.0, 0.0, 1.0]);
+        let heightfield = Heightfield::from_raw(&path, Vector2::new(2, 2), Vector3::new(0.0, 1.0, 0.0), Vector2::new(2.0, 2.0), 2.0).unwrap();
+        assert_eq!(heightfield.height(1.0, 1.0), 2.0);
+        assert_eq!(heightfield.height(5.0, 0.0), 3.0);
+        assert_eq!(heightfield.gradient(1.0, 1.0), Vector2::new(1.0, 0.0));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,73 @@
+use crate::particle::Particle;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Snow,
+    Water,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Thermal {
+    pub melting_point: f32,
+    /// Energy per unit mass needed to turn snow into water at the melting point.
+    pub latent_heat: f32,
+    pub heat_capacity_snow: f32,
+    pub heat_capacity_water: f32,
+    pub conductivity_snow: f32,
+    pub conductivity_water: f32,
+}
+
+impl Thermal {
+    pub fn new(melting_point: f32, latent_heat: f32, heat_capacity_snow: f32, heat_capacity_water: f32, conductivity_snow: f32, conductivity_water: f32) -> Self {
+        Thermal {
+            melting_point,
+            latent_heat,
+            heat_capacity_snow,
+            heat_capacity_water,
+            conductivity_snow,
+            conductivity_water,
+        }
+    }
+
+    pub fn heat_capacity(&self, phase: Phase) -> f32 {
+        match phase {
+            Phase::Snow => self.heat_capacity_snow,
+            Phase::Water => self.heat_capacity_water,
+        }
+    }
+
+    pub fn conductivity(&self, phase: Phase) -> f32 {
+        match phase {
+            Phase::Snow => self.conductivity_snow,
+            Phase::Water => self.conductivity_water,
+        }
+    }
+
+    /// Moves energy between the particle's temperature and its latent heat buffer when it crosses
+    /// the melting point, switching the phase once the buffer is full (melting) or empty (refreezing).
+    pub fn update_phase(&self, particle: &mut Particle) {
+        match particle.phase {
+            Phase::Snow if particle.temperature > self.melting_point => {
+                particle.latent_heat += (particle.temperature - self.melting_point) * self.heat_capacity_snow;
+                particle.temperature = self.melting_point;
+                if particle.latent_heat >= self.latent_heat {
+                    let excess = particle.latent_heat - self.latent_heat;
+                    particle.latent_heat = self.latent_heat;
+                    particle.temperature += excess / self.heat_capacity_water;
+                    particle.phase = Phase::Water;
+                }
+            }
+            Phase::Water if particle.temperature < self.melting_point => {
+                particle.latent_heat -= (self.melting_point - particle.temperature) * self.heat_capacity_water;
+                particle.temperature = self.melting_point;
+                if particle.latent_heat <= 0.0 {
+                    let deficit = -particle.latent_heat;
+                    particle.latent_heat = 0.0;
+                    particle.temperature -= deficit / self.heat_capacity_snow;
+                    particle.phase = Phase::Snow;
+                }
+            }
+            _ => {}
+        }
+    }
+}
\ No newline at end of file
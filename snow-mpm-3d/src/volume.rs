@@ -0,0 +1,142 @@
+use nalgebra::{UnitQuaternion, Vector2, Vector3};
+use crate::collider::Shape;
+
+/// Solid volumes for the initial snow bodies, built from primitives centred on the origin and combined with
+/// constructive solid geometry, e.g. a hollowed igloo is a half ellipsoid minus a smaller one plus a tunnel.
+#[derive(Debug, Clone)]
+pub enum Volume {
+    Sphere { radius: f32 },
+    Box { half_extents: Vector3<f32> },
+    /// Upright along the y axis.
+    Cylinder { half_height: f32, radius: f32 },
+    /// Base of `radius` at `y = -half_height`, apex at `y = half_height`.
+    Cone { half_height: f32, radius: f32 },
+    /// Lying in the xz plane.
+    Torus { major_radius: f32, minor_radius: f32 },
+    Ellipsoid { radii: Vector3<f32> },
+    /// Layer of snow between `y = -thickness / 2` and `y = thickness / 2`, unbounded in x and z. Filling it
+    /// covers the whole domain, so tilt it for a slab on a slope or intersect it with another volume.
+    Slab { thickness: f32 },
+    Union(Box<Volume>, Box<Volume>),
+    Intersection(Box<Volume>, Box<Volume>),
+    /// The first volume with the second one cut out of it.
+    Difference(Box<Volume>, Box<Volume>),
+    /// Scaled, then rotated, then translated.
+    Transformed { volume: Box<Volume>, translation: Vector3<f32>, rotation: UnitQuaternion<f32>, scale: f32 },
+}
+
+impl Volume {
+    pub fn union(self, other: Volume) -> Volume {
+        Volume::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: Volume) -> Volume {
+        Volume::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn difference(self, other: Volume) -> Volume {
+        Volume::Difference(Box::new(self), Box::new(other))
+    }
+
+    pub fn transformed(self, translation: Vector3<f32>, rotation: UnitQuaternion<f32>, scale: f32) -> Volume {
+        Volume::Transformed {
+            volume: Box::new(self),
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn translated(self, translation: Vector3<f32>) -> Volume {
+        self.transformed(translation, UnitQuaternion::identity(), 1.0)
+    }
+
+    pub fn rotated(self, rotation: UnitQuaternion<f32>) -> Volume {
+        self.transformed(Vector3::zeros(), rotation, 1.0)
+    }
+
+    /// Signed distance to the surface, negative inside. Exact for the primitives except the ellipsoid, and a
+    /// bound for the CSG operations, which is all filling a volume needs.
+    pub fn signed_distance(&self, p: Vector3<f32>) -> f32 {
+        match self {
+            Volume::Sphere { radius } => Shape::Sphere { radius: *radius }.signed_distance(p),
+            Volume::Box { half_extents } => Shape::Box { half_extents: *half_extents }.signed_distance(p),
+            Volume::Cylinder { half_height, radius } => Shape::Cylinder { half_height: *half_height, radius: *radius }.signed_distance(p),
+            Volume::Torus { major_radius, minor_radius } => Shape::Torus { major_radius: *major_radius, minor_radius: *minor_radius }.signed_distance(p),
+            Volume::Cone { half_height, radius } => {
+                // Capped cone with a zero top radius, in the plane spanned by the axis and the point
+                let q = Vector2::new(Vector2::new(p.x, p.z).norm(), p.y);
+                let k1 = Vector2::new(0.0, *half_height);
+                let k2 = Vector2::new(-radius, 2.0 * half_height);
+                let ca = Vector2::new(q.x - q.x.min(if q.y < 0.0 { *radius } else { 0.0 }), q.y.abs() - half_height);
+                let cb = q - k1 + k2 * ((k1 - q).dot(&k2) / k2.norm_squared()).clamp(0.0, 1.0);
+                let sign = if cb.x < 0.0 && ca.y < 0.0 { -1.0 } else { 1.0 };
+                sign * ca.norm_squared().min(cb.norm_squared()).sqrt()
+            }
+            Volume::Ellipsoid { radii } => {
+                let k0 = p.component_div(radii).norm();
+                let k1 = p.component_div(&radii.component_mul(radii)).norm();
+                if k1 > 0.0 { k0 * (k0 - 1.0) / k1 } else { -radii.min() }
+            }
+            Volume::Slab { thickness } => p.y.abs() - thickness / 2.0,
+            Volume::Union(a, b) => a.signed_distance(p).min(b.signed_distance(p)),
+            Volume::Intersection(a, b) => a.signed_distance(p).max(b.signed_distance(p)),
+            Volume::Difference(a, b) => a.signed_distance(p).max(-b.signed_distance(p)),
+            Volume::Transformed { volume, translation, rotation, scale } => {
+                volume.signed_distance(rotation.inverse() * (p - translation) / *scale) * scale
+            }
+        }
+    }
+
+    pub fn contains(&self, p: Vector3<f32>) -> bool {
+        self.signed_distance(p) <= 0.0
+    }
+
+    /// Axis-aligned bounding box, infinite along the axes a slab extends to.
+    pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let centred = |half: Vector3<f32>| (-half, half);
+        match self {
+            Volume::Sphere { radius } => centred(Vector3::repeat(*radius)),
+            Volume::Box { half_extents } => centred(*half_extents),
+            Volume::Cylinder { half_height, radius } | Volume::Cone { half_height, radius } => centred(Vector3::new(*radius, *half_height, *radius)),
+            Volume::Torus { major_radius, minor_radius } => centred(Vector3::new(major_radius + minor_radius, *minor_radius, major_radius + minor_radius)),
+            Volume::Ellipsoid { radii } => centred(*radii),
+            Volume::Slab { thickness } => centred(Vector3::new(f32::INFINITY, thickness / 2.0, f32::INFINITY)),
+            Volume::Union(a, b) => {
+                let (a_min, a_max) = a.bounds();
+                let (b_min, b_max) = b.bounds();
+                (a_min.inf(&b_min), a_max.sup(&b_max))
+            }
+            Volume::Intersection(a, b) => {
+                let (a_min, a_max) = a.bounds();
+                let (b_min, b_max) = b.bounds();
+                (a_min.sup(&b_min), a_max.inf(&b_max))
+            }
+            Volume::Difference(a, _) => a.bounds(),
+            Volume::Transformed { volume, translation, rotation, scale } => {
+                let (min, max) = volume.bounds();
+                let finite = min.iter().chain(max.iter()).all(|c| c.is_finite());
+                if !finite {
+                    // Rotating an unbounded box would mix infinities, so only an unrotated one keeps finite axes
+                    if *rotation != UnitQuaternion::identity() {
+                        return centred(Vector3::repeat(f32::INFINITY));
+                    }
+                    return (min * *scale + translation, max * *scale + translation);
+                }
+                let mut world_min = Vector3::repeat(f32::INFINITY);
+                let mut world_max = Vector3::repeat(f32::NEG_INFINITY);
+                for corner in 0..8 {
+                    let local = Vector3::new(
+                        if corner & 1 == 0 { min.x } else { max.x },
+                        if corner & 2 == 0 { min.y } else { max.y },
+                        if corner & 4 == 0 { min.z } else { max.z },
+                    );
+                    let world = rotation * (local * *scale) + translation;
+                    world_min = world_min.inf(&world);
+                    world_max = world_max.sup(&world);
+                }
+                (world_min, world_max)
+            }
+        }
+    }
+}
\ No newline at end of file
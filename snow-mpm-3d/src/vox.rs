@@ -0,0 +1,205 @@
+use std::io;
+use std::path::Path;
+use nalgebra::Vector3;
+
+/// Snow that the voxels of one palette index are filled with.
+#[derive(Debug, Clone, Copy)]
+pub struct VoxMaterial {
+    pub density: f32,
+    pub temperature: f32,
+    /// Multipliers on the Young's modulus and the critical compression and stretch, like `Heterogeneity` applies.
+    pub stiffness: f32,
+    pub strength: f32,
+}
+
+impl VoxMaterial {
+    pub fn new(density: f32, temperature: f32) -> Self {
+        VoxMaterial {
+            density,
+            temperature,
+            stiffness: 1.0,
+            strength: 1.0,
+        }
+    }
+
+    pub fn with_material(mut self, stiffness: f32, strength: f32) -> Self {
+        self.stiffness = stiffness;
+        self.strength = strength;
+        self
+    }
+}
+
+/// One model of a MagicaVoxel file, with its axes turned so y points up like in the grid. Voxel `(i, j, k)` fills
+/// the cube from `origin + (i, j, k) * voxel_size` to `origin + (i + 1, j + 1, k + 1) * voxel_size`.
+#[derive(Debug, Clone)]
+pub struct VoxModel {
+    pub size: Vector3<usize>,
+    /// Palette index of every voxel, `None` where the model is empty, x fastest then y then z.
+    pub voxels: Vec<Option<u8>>,
+    pub origin: Vector3<f32>,
+    pub voxel_size: f32,
+    /// Materials by palette index. Voxels with an index that is not listed use `default_material`, or stay empty.
+    pub materials: Vec<(u8, VoxMaterial)>,
+    pub default_material: Option<VoxMaterial>,
+}
+
+impl VoxModel {
+    /// Places the model in grid coordinates.
+    pub fn placed(mut self, origin: Vector3<f32>, voxel_size: f32) -> Self {
+        self.origin = origin;
+        self.voxel_size = voxel_size;
+        self
+    }
+
+    pub fn with_material(mut self, index: u8, material: VoxMaterial) -> Self {
+        self.materials.push((index, material));
+        self
+    }
+
+    pub fn with_default_material(mut self, material: VoxMaterial) -> Self {
+        self.default_material = Some(material);
+        self
+    }
+
+    pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+        (self.origin, self.origin + self.size.cast::<f32>() * self.voxel_size)
+    }
+
+    /// Palette index of the voxel containing `position`, if it is filled.
+    pub fn palette_index(&self, position: Vector3<f32>) -> Option<u8> {
+        let cell = (position - self.origin) / self.voxel_size;
+        if cell.iter().any(|&c| c < 0.0) {
+            return None;
+        }
+        let cell = cell.map(|c| c as usize);
+        if cell.x >= self.size.x || cell.y >= self.size.y || cell.z >= self.size.z {
+            return None;
+        }
+        self.voxels[cell.x + self.size.x * (cell.y + self.size.y * cell.z)]
+    }
+
+    pub fn material(&self, index: u8) -> Option<VoxMaterial> {
+        self.materials.iter().find(|(i, _)| *i == index).map(|(_, material)| *material).or(self.default_material)
+    }
+
+    /// Number of filled voxels for every palette index.
+    pub fn counts(&self) -> [usize; 256] {
+        let mut counts = [0; 256];
+        for index in self.voxels.iter().flatten() {
+            counts[*index as usize] += 1;
+        }
+        counts
+    }
+}
+
+/// Contents of a MagicaVoxel `.vox` file. Only the `SIZE`, `XYZI` and `RGBA` chunks are read, the scene graph that
+/// arranges several models is ignored, so every model is placed on its own.
+pub struct VoxFile {
+    pub models: Vec<VoxModel>,
+    /// RGBA colour of every palette index, where index 0 is unused as in MagicaVoxel.
+    pub palette: [[u8; 4]; 256],
+}
+
+impl VoxFile {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid VOX file: {}", message));
+        let bytes = std::fs::read(path)?;
+        let read_u32 = |offset: usize| -> io::Result<usize> {
+            bytes.get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+                .ok_or_else(|| invalid("unexpected end of file"))
+        };
+
+        if bytes.get(0..4) != Some(b"VOX ") {
+            return Err(invalid("missing VOX header"));
+        }
+
+        let mut models = Vec::new();
+        let mut size = None;
+        let mut palette = default_palette();
+        // Skip the header and the MAIN chunk header, its children follow directly
+        let mut offset = 8 + 12;
+        while offset + 12 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let content_size = read_u32(offset + 4)?;
+            let content = offset + 12;
+            if content + content_size > bytes.len() {
+                return Err(invalid("chunk runs past the end of the file"));
+            }
+            match id {
+                b"SIZE" => {
+                    let (x, y, z) = (read_u32(content)?, read_u32(content + 4)?, read_u32(content + 8)?);
+                    // MagicaVoxel is z up, turn it to y up and keep the handedness
+                    size = Some((Vector3::new(x, y, z), Vector3::new(x, z, y)));
+                }
+                b"XYZI" => {
+                    let (vox_size, grid_size) = size.take().ok_or_else(|| invalid("XYZI chunk without SIZE"))?;
+                    let count = read_u32(content)?;
+                    if 4 + 4 * count > content_size {
+                        return Err(invalid("XYZI chunk too short"));
+                    }
+                    let mut voxels = vec![None; grid_size.x * grid_size.y * grid_size.z];
+                    for v in bytes[content + 4..content + 4 + 4 * count].chunks_exact(4) {
+                        let (x, y, z) = (v[0] as usize, v[1] as usize, v[2] as usize);
+                        if x >= vox_size.x || y >= vox_size.y || z >= vox_size.z {
+                            return Err(invalid("voxel outside the model"));
+                        }
+                        let (i, j, k) = (x, z, vox_size.y - 1 - y);
+                        voxels[i + grid_size.x * (j + grid_size.y * k)] = Some(v[3]);
+                    }
+                    models.push(VoxModel {
+                        size: grid_size,
+                        voxels,
+                        origin: Vector3::zeros(),
+                        voxel_size: 1.0,
+                        materials: Vec::new(),
+                        default_material: None,
+                    });
+                }
+                b"RGBA" => {
+                    // Colour i of the chunk belongs to palette index i + 1
+                    for (i, color) in bytes[content..content + content_size].chunks_exact(4).take(255).enumerate() {
+                        palette[i + 1] = [color[0], color[1], color[2], color[3]];
+                    }
+                }
+                _ => {}
+            }
+            offset = content + content_size + read_u32(offset + 8)?;
+        }
+
+        if models.is_empty() {
+            return Err(invalid("no models"));
+        }
+        Ok(VoxFile {
+            models,
+            palette,
+        })
+    }
+}
+
+/// Grey ramp used when the file has no `RGBA` chunk, enough to tell the indices apart.
+fn default_palette() -> [[u8; 4]; 256] {
+    let mut palette = [[0; 4]; 256];
+    for (i, color) in palette.iter_mut().enumerate() {
+        *color = [i as u8, i as u8, i as u8, 255];
+    }
+    palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_the_fort() {
+        let file = VoxFile::load(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/fort.vox")).unwrap();
+        let fort = &file.models[0];
+        // 16 x 16 x 8 in MagicaVoxel, with its z axis turned up
+        assert_eq!(fort.size, Vector3::new(16, 8, 16));
+        assert_eq!(fort.counts().iter().sum::<usize>(), 752);
+        // The corner column of the file is packed snow at the bottom two voxels and plain snow above
+        assert_eq!(fort.palette_index(Vector3::new(0.5, 0.5, 15.5)), Some(2));
+        assert_eq!(fort.palette_index(Vector3::new(0.5, 1.5, 15.5)), Some(2));
+        assert_eq!(fort.palette_index(Vector3::new(0.5, 2.5, 15.5)), Some(1));
+    }
+}
\ No newline at end of file